@@ -0,0 +1,48 @@
+// Compares searching a stream for a Pattern with searching for its compiled form,
+// which skips ahead by whole windows of the Pattern's length. Run with
+// "cargo bench".
+
+use ppatch::prelude::*;
+use ppatch::Pattern;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+// Returns the fastest of a few runs of search, which returns the count of results
+fn fastest(mut search: impl FnMut() -> usize) -> Duration {
+    (0..3)
+        .map(|_| {
+            let started = Instant::now();
+            assert!(search() > 0);
+            started.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let mut value: u32 = 1;
+    let input: Vec<u8> = (0..8 << 20)
+        .map(|_| {
+            value = value.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (value >> 16) as u8
+        })
+        .collect();
+
+    for len in &[8, 32] {
+        let string: String = (0..*len)
+            .map(|index| format!("0x{:02x} ", (index * 37 + 11) % 256))
+            .collect();
+        let pattern = Pattern::<u8>::from_str(&string).unwrap();
+        let compiled = pattern.compile();
+
+        let plain = fastest(|| input.iter().copied().search_pattern(&pattern).count());
+        let skipping = fastest(|| input.iter().copied().search_pattern(&compiled).count());
+        println!(
+            "{} byte pattern in {} MB: plain {:?}, compiled {:?}",
+            len,
+            input.len() >> 20,
+            plain,
+            skipping
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use crate::MaskedByte;
+use std::fmt;
+use std::ops;
+use std::str;
+
+/// Alternatives matches a single byte matching any of its MaskedBytes.
+#[derive(PartialEq, Clone)]
+pub struct Alternatives<T> {
+    values: Vec<MaskedByte<T>>,
+}
+
+impl<T> fmt::Debug for Alternatives<T>
+where
+    T: fmt::Binary + num::PrimInt,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Alternatives {:?}", self.values)
+    }
+}
+
+impl<T> fmt::Display for Alternatives<T>
+where
+    T: num::PrimInt + ops::ShrAssign<u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        for (index, value) in self.values.iter().enumerate() {
+            if index > 0 {
+                write!(f, "|")?;
+            }
+            write!(f, "{}", value)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl<T> Alternatives<T>
+where
+    T: From<u8>
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    pub fn new(values: Vec<MaskedByte<T>>) -> Self {
+        Self { values }
+    }
+
+    pub fn values(&self) -> &[MaskedByte<T>] {
+        &self.values
+    }
+
+    /// Returns true if any of the MaskedBytes matches data
+    pub fn matches(&self, data: T) -> bool {
+        self.values.iter().any(|value| value.matches(data))
+    }
+
+    /// Returns true if any data matches
+    pub fn matches_any(&self) -> bool {
+        self.values.iter().any(MaskedByte::matches_any)
+    }
+}
+
+impl<T> str::FromStr for Alternatives<T>
+where
+    T: From<u8>
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    type Err = crate::Error;
+
+    /// Creates Alternatives from MaskedBytes separated by "|" and enclosed in
+    /// parentheses, for example "(0x40|0x80|0x1?)".
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let inner = string
+            .strip_prefix('(')
+            .and_then(|inner| inner.strip_suffix(')'))
+            .filter(|inner| !inner.trim().is_empty())
+            .ok_or_else(|| crate::Error::InvalidAlternatives {
+                string: string.to_string(),
+            })?;
+
+        let mut values = Vec::new();
+        for part in inner.split('|') {
+            values.push(MaskedByte::from_str(part.trim())?);
+        }
+        Ok(Self::new(values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn matches() {
+        let a = Alternatives::<u8>::from_str("(0x40|0x80|0x1?)").unwrap();
+        assert!(a.matches(0x40));
+        assert!(a.matches(0x80));
+        assert!(a.matches(0x1f));
+        assert!(!a.matches(0x41));
+        assert!(!a.matches_any());
+    }
+
+    #[test]
+    fn string_conversion() {
+        let a = Alternatives::<u8>::from_str("( 0x40 | 0b1??????? )").unwrap();
+        assert_eq!(
+            a.values(),
+            [MaskedByte::new(0x40, 0xff), MaskedByte::new(0x80, 0x80)]
+        );
+        assert_eq!(a.to_string(), "(01000000|1???????)");
+        assert!(Alternatives::<u8>::from_str("()").is_err());
+        assert!(Alternatives::<u8>::from_str("(0x40|)").is_err());
+        assert!(Alternatives::<u8>::from_str("(0x40").is_err());
+    }
+}
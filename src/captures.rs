@@ -0,0 +1,61 @@
+use std::ops::Range;
+
+/// Captures holds the parts of a match captured by the groups of a Pattern. Groups
+/// are numbered in order of their opening parenthesis, starting with 0. Ranges are
+/// offsets into the data of the match.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct Captures {
+    names: Vec<Option<String>>,
+    ranges: Vec<Option<Range<usize>>>,
+}
+
+impl Captures {
+    pub(crate) fn new(names: Vec<Option<String>>, mut ranges: Vec<Option<Range<usize>>>) -> Self {
+        ranges.resize(names.len(), None);
+        Self { names, ranges }
+    }
+
+    /// Returns count of groups
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns range captured by group index, None if the group did not take part
+    /// in the match
+    pub fn get(&self, index: usize) -> Option<Range<usize>> {
+        self.ranges.get(index).cloned().flatten()
+    }
+
+    /// Returns range captured by the group with name
+    pub fn name(&self, name: &str) -> Option<Range<usize>> {
+        let index = self
+            .names
+            .iter()
+            .position(|other| other.as_deref() == Some(name))?;
+        self.get(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get() {
+        let captures = Captures::new(
+            vec![None, Some("addr".to_string()), None],
+            vec![Some(0..2), Some(2..6)],
+        );
+        assert_eq!(captures.len(), 3);
+        assert_eq!(captures.get(0), Some(0..2));
+        assert_eq!(captures.get(1), Some(2..6));
+        assert_eq!(captures.get(2), None);
+        assert_eq!(captures.get(3), None);
+        assert_eq!(captures.name("addr"), Some(2..6));
+        assert_eq!(captures.name("other"), None);
+    }
+}
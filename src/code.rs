@@ -0,0 +1,193 @@
+use crate::{Element, MaskedByte, PatternFormatter};
+use std::fmt::Write;
+
+// Signatures as used in native code: a byte string like "\x48\x8B\x00\x05" and a
+// mask like "xx?x" where "x" marks defined and "?" undefined bytes.
+
+// Parses byte string and mask, the byte string may be enclosed in quotes
+pub fn parse(bytes: &str, mask: &str) -> crate::Result<Vec<Element<u8>>> {
+    let invalid = |string: &str| crate::Error::InvalidSignature {
+        string: string.to_string(),
+    };
+
+    let inner = bytes
+        .strip_prefix('"')
+        .and_then(|inner| inner.strip_suffix('"'))
+        .unwrap_or(bytes);
+    let mut values = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let value = match c {
+            '\\' => {
+                let digits: String = chars.by_ref().take(3).collect();
+                digits
+                    .strip_prefix('x')
+                    .filter(|digits| digits.len() == 2)
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                    .ok_or_else(|| invalid(bytes))?
+            }
+            c if c.is_ascii() => c as u8,
+            _ => return Err(invalid(bytes)),
+        };
+        values.push(value);
+    }
+
+    let mask = mask
+        .strip_prefix('"')
+        .and_then(|inner| inner.strip_suffix('"'))
+        .unwrap_or(mask);
+    if mask.chars().count() != values.len() {
+        return Err(invalid(mask));
+    }
+
+    values
+        .into_iter()
+        .zip(mask.chars())
+        .map(|(value, defined)| match defined {
+            'x' => Ok(Element::Byte(MaskedByte::from(value))),
+            '?' => Ok(Element::Byte(MaskedByte::default())),
+            _ => Err(invalid(mask)),
+        })
+        .collect()
+}
+
+// Writes bytes as byte string and mask. Undefined bytes are written as "\x00",
+// partly defined bytes can not be written. Bytes come with the index of the
+// element they belong to, which errors report.
+pub fn format(bytes: &[(usize, MaskedByte<u8>)]) -> crate::Result<(String, String)> {
+    let mut string = String::new();
+    let mut mask = String::new();
+    for (index, byte) in bytes {
+        match byte.mask() {
+            0xff => {
+                write!(string, "\\x{:02X}", byte.value()).unwrap();
+                mask.push('x');
+            }
+            0 => {
+                string.push_str("\\x00");
+                mask.push('?');
+            }
+            _ => {
+                return Err(crate::Error::FormatUnsupported {
+                    element: PatternFormatter::new().masked_byte(byte),
+                    index: *index,
+                })
+            }
+        }
+    }
+    Ok((string, mask))
+}
+
+// Writes values as comma separated hex numbers
+fn list(values: impl Iterator<Item = u8>) -> String {
+    values
+        .map(|value| format!("0x{:02x}", value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// True if name is an ASCII identifier as C, Rust and YARA accept it
+pub fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .enumerate()
+        .all(|(index, c)| c == '_' || c.is_ascii_alphabetic() || (index > 0 && c.is_ascii_digit()))
+        && !name.is_empty()
+}
+
+fn check_name(name: &str) -> crate::Result<()> {
+    if is_identifier(name) {
+        Ok(())
+    } else {
+        Err(crate::Error::InvalidIdentifier {
+            string: name.to_string(),
+        })
+    }
+}
+
+// Writes value and mask arrays as C/C++ definitions, name needs to be an identifier
+pub fn c_arrays(bytes: &[MaskedByte<u8>], name: &str) -> crate::Result<String> {
+    check_name(name)?;
+    Ok(format!(
+        "const unsigned char {0}_bytes[{1}] = {{ {2} }};\nconst unsigned char {0}_mask[{1}] = {{ {3} }};\n",
+        name,
+        bytes.len(),
+        list(bytes.iter().map(|byte| byte.value() & byte.mask())),
+        list(bytes.iter().map(MaskedByte::mask)),
+    ))
+}
+
+// Writes value and mask arrays as Rust constants, name needs to be an identifier
+pub fn rust_arrays(bytes: &[MaskedByte<u8>], name: &str) -> crate::Result<String> {
+    check_name(name)?;
+    Ok(format!(
+        "const {0}_BYTES: [u8; {1}] = [{2}];\nconst {0}_MASK: [u8; {1}] = [{3}];\n",
+        name.to_uppercase(),
+        bytes.len(),
+        list(bytes.iter().map(|byte| byte.value() & byte.mask())),
+        list(bytes.iter().map(MaskedByte::mask)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_code() {
+        let elements = parse(r#""\x48\x8B\x00\x00A""#, "xx??x").unwrap();
+        assert_eq!(
+            elements,
+            vec![
+                Element::Byte(MaskedByte::new(0x48, 0xff)),
+                Element::Byte(MaskedByte::new(0x8b, 0xff)),
+                Element::Byte(MaskedByte::new(0x00, 0x00)),
+                Element::Byte(MaskedByte::new(0x00, 0x00)),
+                Element::Byte(MaskedByte::new(0x41, 0xff)),
+            ]
+        );
+
+        assert!(parse(r"\x48\x8B", "x").is_err());
+        assert!(parse(r"\x48\x8", "xx").is_err());
+        assert!(parse(r"\x48", "y").is_err());
+    }
+
+    #[test]
+    fn format_code() {
+        let bytes = [
+            MaskedByte::new(0x48, 0xff),
+            MaskedByte::new(0x12, 0x00),
+            MaskedByte::new(0x05, 0xff),
+        ];
+        let indexed: Vec<_> = bytes.iter().copied().enumerate().collect();
+        assert_eq!(
+            format(&indexed).unwrap(),
+            (r"\x48\x00\x05".to_string(), "x?x".to_string())
+        );
+        assert_eq!(
+            format(&[(1, MaskedByte::new(0x40, 0xf0))])
+                .unwrap_err()
+                .to_string(),
+            "Element 0x4? at index 1 can not be written in this format"
+        );
+
+        assert_eq!(
+            c_arrays(&bytes, "sig").unwrap(),
+            "const unsigned char sig_bytes[3] = { 0x48, 0x00, 0x05 };\n\
+             const unsigned char sig_mask[3] = { 0xff, 0x00, 0xff };\n"
+        );
+        assert_eq!(
+            rust_arrays(&bytes, "sig").unwrap(),
+            "const SIG_BYTES: [u8; 3] = [0x48, 0x00, 0x05];\n\
+             const SIG_MASK: [u8; 3] = [0xff, 0x00, 0xff];\n"
+        );
+
+        assert!(c_arrays(&bytes, "_sig2").is_ok());
+        for name in &["my-sig", "1abc", "", "sig name", "ä"] {
+            assert!(matches!(
+                c_arrays(&bytes, name),
+                Err(crate::Error::InvalidIdentifier { .. })
+            ));
+            assert!(rust_arrays(&bytes, name).is_err());
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::Pattern;
+use std::fmt;
+use std::ops;
+
+/// CompiledPattern is a Pattern with a precomputed Horspool shift table taking
+/// masks into account. Searching with it skips ahead by more than one byte on
+/// a mismatch. This is only possible if every element of the Pattern matches
+/// exactly one byte, else the shift is always 1.
+pub struct CompiledPattern<T> {
+    pattern: Pattern<T>,
+
+    // Shift for byte values fitting into the table, empty if pattern has elements
+    // not matching exactly one byte
+    shifts: Vec<usize>,
+
+    // Elements from this index to the end of pattern (except last) are checked when
+    // computing the shift for values not fitting into the table. Before it there is
+    // a fully undefined element matching anything.
+    first: usize,
+}
+
+// Values covered by the shift table
+const TABLE_SIZE: usize = 256;
+
+impl<T> CompiledPattern<T>
+where
+    T: From<u8>
+        + fmt::Binary
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + ops::ShlAssign<u32>
+        + PartialEq
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>
+        + ops::BitOrAssign
+        + ops::BitAndAssign,
+{
+    pub fn new(pattern: Pattern<T>) -> Self {
+        let len = pattern.element_count();
+
+        // An element matching everything limits all shifts
+        let first = (0..len.saturating_sub(1))
+            .rev()
+            .find(|index| pattern.get(*index).unwrap().matches_any())
+            .unwrap_or(0);
+
+        let mut compiled = Self {
+            pattern,
+            shifts: Vec::new(),
+            first,
+        };
+        if !compiled.pattern.is_simple() {
+            return compiled;
+        }
+        compiled.shifts = (0..TABLE_SIZE)
+            .map(|value| compiled.compute_shift(From::from(value as u8)))
+            .collect();
+        compiled
+    }
+
+    pub fn pattern(&self) -> &Pattern<T> {
+        &self.pattern
+    }
+
+    pub fn element_count(&self) -> usize {
+        self.pattern.element_count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_empty()
+    }
+
+    /// Returns how far a search window can be moved if the last byte of the window
+    /// is byte and the window is no match. That is the distance of the last element
+    /// (not counting the last one) matching byte to the end of pattern.
+    pub fn shift(&self, byte: T) -> usize {
+        if self.shifts.is_empty() {
+            return 1;
+        }
+
+        match byte.to_usize() {
+            Some(index) if index < self.shifts.len() => self.shifts[index],
+            _ => self.compute_shift(byte),
+        }
+    }
+
+    fn compute_shift(&self, byte: T) -> usize {
+        let len = self.pattern.element_count();
+        (self.first..len.saturating_sub(1))
+            .rev()
+            .find(|index| self.pattern.get(*index).unwrap().matches(byte))
+            .map_or(len.max(1), |index| len - 1 - index)
+    }
+}
+
+impl<T> fmt::Debug for CompiledPattern<T>
+where
+    T: fmt::Binary + num::PrimInt,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CompiledPattern [{:?}]", self.pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn shifts() {
+        let p = Pattern::<u8>::from_str("0x41 0x4? 0x43 0x44")
+            .unwrap()
+            .compile();
+        assert_eq!(p.shift(0x44), 2);
+        assert_eq!(p.shift(0x43), 1);
+        assert_eq!(p.shift(0x42), 2);
+        assert_eq!(p.shift(0x41), 2);
+        assert_eq!(p.shift(0x00), 4);
+    }
+
+    #[test]
+    fn shifts_wildcard() {
+        let p = Pattern::<u8>::from_str("0x41 0x?? 0x43 0x44")
+            .unwrap()
+            .compile();
+        assert_eq!(p.shift(0x00), 2);
+        assert_eq!(p.shift(0x43), 1);
+    }
+
+    #[test]
+    fn shifts_big_values() {
+        let p = Pattern::<u16>::from_str("0x1234 0x12?? 0x0000")
+            .unwrap()
+            .compile();
+        assert_eq!(p.shift(0x1234), 1);
+        assert_eq!(p.shift(0x5678), 3);
+        assert_eq!(p.shift(0x0000), 3);
+    }
+}
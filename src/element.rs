@@ -0,0 +1,639 @@
+use crate::{Alternatives, Literal, MaskedByte, Typed};
+use std::fmt;
+use std::ops;
+use std::str;
+use std::str::FromStr;
+
+/// Element is a single part of a Pattern.
+#[derive(PartialEq, Clone)]
+pub enum Element<T> {
+    /// Matches a single byte
+    Byte(MaskedByte<T>),
+
+    /// Matches a single byte matching one of several MaskedBytes
+    Alternatives(Alternatives<T>),
+
+    /// Matches a single byte with a value from min to max, both inclusive
+    Range { min: T, max: T },
+
+    /// Matches a single byte not matching the single byte element
+    Not(Box<Element<T>>),
+
+    /// Matches any min to max bytes. The shortest possible gap is taken.
+    Gap { min: usize, max: usize },
+
+    /// Matches a single byte element min to max times, max None means unlimited.
+    /// A greedy repeat takes as many bytes as possible and needs to see the end of
+    /// the run before it matches, a lazy one takes as few as possible.
+    Repeat {
+        element: Box<Element<T>>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+
+    /// Matches the bytes of an integer stored with given type and endianness
+    Typed(Typed<T>),
+
+    /// Matches the bytes of a string
+    Literal(Literal<T>),
+
+    /// Matches at the start of input without consuming a byte
+    Start,
+
+    /// Matches at the end of input without consuming a byte
+    End,
+
+    /// Matches its elements and captures the matched bytes. Index is the position
+    /// of the group in the Pattern, counting opening parentheses from 0.
+    Group {
+        index: usize,
+        name: Option<String>,
+        elements: Vec<Element<T>>,
+    },
+
+    /// Matches without consuming bytes if its elements match the bytes after the
+    /// current position or, with behind, the bytes right before it. A negative
+    /// assertion matches if its elements do not. Groups inside do not capture.
+    Assertion {
+        behind: bool,
+        negative: bool,
+        elements: Vec<Element<T>>,
+    },
+}
+
+// Minimal and maximal count of a repeat, max None means unlimited
+type Bounds = (usize, Option<usize>);
+
+// Writes the quantifier of a repeat like "*", "{2,4}" or "+?"
+pub(crate) fn quantifier(min: usize, max: Option<usize>, greedy: bool) -> String {
+    let quantifier = match (min, max) {
+        (0, None) => "*".to_string(),
+        (1, None) => "+".to_string(),
+        (min, None) => format!("{{{},}}", min),
+        (min, Some(max)) if min == max => format!("{{{}}}", min),
+        (min, Some(max)) => format!("{{{},{}}}", min, max),
+    };
+    if greedy {
+        quantifier
+    } else {
+        quantifier + "?"
+    }
+}
+
+// Minimal and maximal count of bytes a sequence of elements matches
+pub(crate) fn sequence_len<T>(elements: &[Element<T>]) -> Bounds
+where
+    T: From<u8>
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    elements.iter().fold((0, Some(0)), |(min, max), element| {
+        let (element_min, element_max) = element.len_range();
+        (
+            min.saturating_add(element_min),
+            max.zip(element_max).map(|(a, b)| a.saturating_add(b)),
+        )
+    })
+}
+
+impl<T> fmt::Debug for Element<T>
+where
+    T: fmt::Binary + num::PrimInt,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Element::Byte(masked_byte) => write!(f, "{:?}", masked_byte),
+            Element::Alternatives(alternatives) => write!(f, "{:?}", alternatives),
+            Element::Range { min, max } => {
+                write!(f, "Range {{ min: {:#b}, max: {:#b} }}", min, max)
+            }
+            Element::Gap { min, max } => write!(f, "Gap {{ min: {}, max: {} }}", min, max),
+            Element::Repeat {
+                element,
+                min,
+                max,
+                greedy,
+            } => write!(
+                f,
+                "Repeat {{ element: {:?}, min: {}, max: {:?}, greedy: {} }}",
+                element, min, max, greedy
+            ),
+            Element::Not(element) => write!(f, "Not({:?})", element),
+            Element::Typed(typed) => write!(f, "{:?}", typed),
+            Element::Literal(literal) => write!(f, "{:?}", literal),
+            Element::Start => write!(f, "Start"),
+            Element::End => write!(f, "End"),
+            Element::Group {
+                index,
+                name,
+                elements,
+            } => write!(
+                f,
+                "Group {{ index: {}, name: {:?}, elements: {:?} }}",
+                index, name, elements
+            ),
+            Element::Assertion {
+                behind,
+                negative,
+                elements,
+            } => write!(
+                f,
+                "Assertion {{ behind: {}, negative: {}, elements: {:?} }}",
+                behind, negative, elements
+            ),
+        }
+    }
+}
+
+impl<T> fmt::Display for Element<T>
+where
+    T: num::PrimInt + ops::ShrAssign<u8>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Element::Byte(masked_byte) => write!(f, "{}", masked_byte),
+            Element::Alternatives(alternatives) => write!(f, "{}", alternatives),
+            Element::Range { min, max } => {
+                write!(f, "{}-{}", MaskedByte::from(*min), MaskedByte::from(*max))
+            }
+            Element::Gap { min, max } if min == max => write!(f, "[{}]", min),
+            Element::Gap { min, max } => write!(f, "[{}-{}]", min, max),
+            Element::Repeat {
+                element,
+                min,
+                max,
+                greedy,
+            } => {
+                write!(f, "{}{}", element, quantifier(*min, *max, *greedy))
+            }
+            Element::Not(element) => write!(f, "!{}", element),
+            Element::Typed(typed) => write!(f, "{}", typed),
+            Element::Literal(literal) => write!(f, "{}", literal),
+            Element::Start => write!(f, "^"),
+            Element::End => write!(f, "$"),
+            Element::Group { name, elements, .. } => {
+                match name {
+                    Some(name) => write!(f, "(?<{}> ", name)?,
+                    None => write!(f, "( ")?,
+                }
+                for element in elements {
+                    write!(f, "{} ", element)?;
+                }
+                write!(f, ")")
+            }
+            Element::Assertion {
+                behind,
+                negative,
+                elements,
+            } => {
+                write!(
+                    f,
+                    "(?{}{} ",
+                    if *behind { "<" } else { "" },
+                    if *negative { "!" } else { "=" }
+                )?;
+                for element in elements {
+                    write!(f, "{} ", element)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl<T> Element<T>
+where
+    T: From<u8>
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    /// Returns true if element always matches exactly one byte
+    pub fn is_single(&self) -> bool {
+        match self {
+            Element::Byte(_)
+            | Element::Alternatives(_)
+            | Element::Range { .. }
+            | Element::Not(_) => true,
+            Element::Gap { .. }
+            | Element::Repeat { .. }
+            | Element::Typed(_)
+            | Element::Literal(_)
+            | Element::Start
+            | Element::End
+            | Element::Group { .. }
+            | Element::Assertion { .. } => false,
+        }
+    }
+
+    /// Matches a single byte, elements not matching exactly one byte never match
+    pub fn matches(&self, data: T) -> bool {
+        match self {
+            Element::Byte(masked_byte) => masked_byte.matches(data),
+            Element::Alternatives(alternatives) => alternatives.matches(data),
+            Element::Range { min, max } => *min <= data && data <= *max,
+            Element::Not(element) => !element.matches(data),
+            Element::Gap { .. }
+            | Element::Repeat { .. }
+            | Element::Typed(_)
+            | Element::Literal(_)
+            | Element::Start
+            | Element::End
+            | Element::Group { .. }
+            | Element::Assertion { .. } => false,
+        }
+    }
+
+    /// Returns true if element matches a single byte whatever its value is
+    pub fn matches_any(&self) -> bool {
+        match self {
+            Element::Byte(masked_byte) => masked_byte.matches_any(),
+            Element::Alternatives(alternatives) => alternatives.matches_any(),
+            Element::Range { min, max } => min.is_zero() && *max == T::max_value(),
+            Element::Not(_)
+            | Element::Gap { .. }
+            | Element::Repeat { .. }
+            | Element::Typed(_)
+            | Element::Literal(_)
+            | Element::Start
+            | Element::End
+            | Element::Group { .. }
+            | Element::Assertion { .. } => false,
+        }
+    }
+
+    /// Returns value if element matches a single fully defined byte
+    pub fn defined(&self) -> Option<T> {
+        match self {
+            Element::Byte(masked_byte) => masked_byte.defined(),
+            Element::Range { min, max } if min == max => Some(*min),
+            Element::Range { .. } => None,
+            Element::Alternatives(_)
+            | Element::Not(_)
+            | Element::Gap { .. }
+            | Element::Repeat { .. }
+            | Element::Typed(_)
+            | Element::Literal(_)
+            | Element::Start
+            | Element::End
+            | Element::Group { .. }
+            | Element::Assertion { .. } => None,
+        }
+    }
+
+    /// Returns the bytes of typed values and literals, which match like a sequence
+    /// of single byte elements
+    pub fn sequence(&self) -> Option<&[Element<T>]> {
+        match self {
+            Element::Typed(typed) => Some(typed.elements()),
+            Element::Literal(literal) => Some(literal.elements()),
+            _ => None,
+        }
+    }
+
+    /// Returns minimal and maximal count of bytes the element matches, the maximum
+    /// is None if unlimited
+    pub fn len_range(&self) -> (usize, Option<usize>) {
+        match self {
+            Element::Byte(_)
+            | Element::Alternatives(_)
+            | Element::Range { .. }
+            | Element::Not(_) => (1, Some(1)),
+            Element::Start | Element::End | Element::Assertion { .. } => (0, Some(0)),
+            Element::Gap { min, max } => (*min, Some(*max)),
+            Element::Repeat { min, max, .. } => (*min, *max),
+            Element::Typed(typed) => (typed.elements().len(), Some(typed.elements().len())),
+            Element::Literal(literal) => (literal.elements().len(), Some(literal.elements().len())),
+            Element::Group { elements, .. } => sequence_len(elements),
+        }
+    }
+
+    /// Returns count of bytes before the current position lookbehind assertions
+    /// inside element may look at
+    pub fn behind(&self) -> usize {
+        match self {
+            Element::Group { elements, .. }
+            | Element::Assertion {
+                behind: false,
+                elements,
+                ..
+            } => elements.iter().map(Element::behind).max().unwrap_or(0),
+            Element::Assertion { elements, .. } => sequence_len(elements)
+                .1
+                .unwrap_or(0)
+                .saturating_add(elements.iter().map(Element::behind).max().unwrap_or(0)),
+            _ => 0,
+        }
+    }
+
+    /// Returns true if element may look at bytes after the ones it matches, like
+    /// the end anchor and lookahead assertions do
+    pub fn ahead(&self) -> bool {
+        match self {
+            Element::End | Element::Assertion { behind: false, .. } => true,
+            Element::Group { elements, .. } | Element::Assertion { elements, .. } => {
+                elements.iter().any(Element::ahead)
+            }
+            Element::Repeat { element, .. } | Element::Not(element) => element.ahead(),
+            _ => false,
+        }
+    }
+
+    // Parses "[n]" or "[min-max]" without the brackets, a gap can not be longer
+    // than a match
+    fn gap_from_str(string: &str) -> Option<Self> {
+        let mut parts = string.splitn(2, '-');
+        let min: usize = parts.next()?.trim().parse().ok()?;
+        let max: usize = match parts.next() {
+            Some(max) => max.trim().parse().ok()?,
+            None => min,
+        };
+
+        if min > max || max > crate::MAX_MATCH_LEN {
+            return None;
+        }
+        Some(Element::Gap { min, max })
+    }
+
+    // Splits string into the repeated part and a trailing quantifier "{n}", "{min,}",
+    // "{min,max}", "*" or "+", each optionally followed by "?" for lazy matching.
+    // Returns None if there is no quantifier. Counts can not exceed the length of
+    // a match.
+    fn split_quantifier(string: &str) -> Option<(&str, Option<Bounds>, bool)> {
+        let (rest, greedy) = match string.strip_suffix('?') {
+            Some(rest) if rest.ends_with(&['}', '*', '+'][..]) => (rest, false),
+            _ => (string, true),
+        };
+
+        if let Some(base) = rest.strip_suffix('*') {
+            return Some((base, Some((0, None)), greedy));
+        }
+        if let Some(base) = rest.strip_suffix('+') {
+            return Some((base, Some((1, None)), greedy));
+        }
+
+        let inner = rest.strip_suffix('}')?;
+        let (base, inner) = inner.split_at(inner.rfind('{')?);
+        let inner = &inner[1..];
+
+        let mut parts = inner.splitn(2, ',');
+        let bounds = parts
+            .next()
+            .and_then(|min| min.trim().parse::<usize>().ok())
+            .and_then(|min| match parts.next().map(str::trim) {
+                None => Some((min, Some(min))),
+                Some("") => Some((min, None)),
+                Some(max) => max.parse::<usize>().ok().map(|max| (min, Some(max))),
+            })
+            .filter(|(min, max)| match max {
+                Some(max) => min <= max && *max <= crate::MAX_MATCH_LEN,
+                None => *min <= crate::MAX_MATCH_LEN,
+            });
+        Some((base, bounds, greedy))
+    }
+
+    // Parses "min-max" with both values fully defined MaskedBytes
+    fn range_from_str(string: &str) -> Option<Self> {
+        let mut parts = string.splitn(2, '-');
+        let min = MaskedByte::<T>::from_str(parts.next()?.trim())
+            .ok()?
+            .defined()?;
+        let max = MaskedByte::<T>::from_str(parts.next()?.trim())
+            .ok()?
+            .defined()?;
+
+        if min > max {
+            return None;
+        }
+        Some(Element::Range { min, max })
+    }
+}
+
+impl<T> str::FromStr for Element<T>
+where
+    T: From<u8>
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    type Err = crate::Error;
+
+    /// Creates Element from string representation. Allowed are MaskedBytes, gaps
+    /// like "[4]" for exactly 4 bytes or "[2-8]" for 2 to 8 bytes of any value and
+    /// alternatives like "(0x40|0x80)", ranges like "0x20-0x7e" and typed values
+    /// like "u32le:0x12??56??". Literals like "\"abc\"" or "u\"abc\"" (UTF-16LE)
+    /// match the bytes of a string. "^" and "$" match at the start and end of input.
+    /// A single byte element preceded by "!" like "!0x00" or "!(0x40|0x80)" matches
+    /// every byte it does not match.
+    /// Single byte elements can be followed by a quantifier like "0xff{16}", "0x00{4,}",
+    /// "0x00{4,8}", "0x??*" or "0x??+". By default repeats are greedy, a trailing "?"
+    /// like "0x??*?" makes them lazy.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "^" => return Ok(Element::Start),
+            "$" => return Ok(Element::End),
+            _ => {}
+        }
+
+        if string.starts_with('"') || string.starts_with("u\"") {
+            return Ok(Element::Literal(Literal::from_str(string)?));
+        }
+
+        if let Some((base, bounds, greedy)) = Self::split_quantifier(string) {
+            let element = Self::from_str(base)?;
+            return match bounds {
+                Some((min, max)) if element.is_single() => Ok(Element::Repeat {
+                    element: Box::new(element),
+                    min,
+                    max,
+                    greedy,
+                }),
+                _ => Err(crate::Error::InvalidQuantifier {
+                    string: string.to_string(),
+                }),
+            };
+        }
+
+        if let Some(negated) = string.strip_prefix('!') {
+            let element = Self::from_str(negated)?;
+            if !element.is_single() {
+                return Err(crate::Error::InvalidNegation {
+                    string: string.to_string(),
+                });
+            }
+            return Ok(Element::Not(Box::new(element)));
+        }
+
+        if string.starts_with('(') {
+            return Ok(Element::Alternatives(Alternatives::from_str(string)?));
+        }
+
+        if string.starts_with('[') {
+            if string.ends_with(']') {
+                if let Some(gap) = Self::gap_from_str(&string[1..string.len() - 1]) {
+                    return Ok(gap);
+                }
+            }
+            return Err(crate::Error::InvalidGap {
+                string: string.to_string(),
+            });
+        }
+
+        if string.contains(':') {
+            return Ok(Element::Typed(Typed::from_str(string)?));
+        }
+
+        if string.contains('-') {
+            return Self::range_from_str(string).ok_or_else(|| crate::Error::InvalidRange {
+                string: string.to_string(),
+            });
+        }
+
+        Ok(Element::Byte(MaskedByte::from_str(string)?))
+    }
+}
+
+impl<T> From<MaskedByte<T>> for Element<T> {
+    fn from(masked_byte: MaskedByte<T>) -> Self {
+        Element::Byte(masked_byte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_conversion_gap() {
+        assert_eq!(
+            Element::<u8>::from_str("[2-8]").unwrap(),
+            Element::Gap { min: 2, max: 8 }
+        );
+        assert_eq!(
+            Element::<u8>::from_str("[4]").unwrap(),
+            Element::Gap { min: 4, max: 4 }
+        );
+        assert!(Element::<u8>::from_str("[8-2]").is_err());
+        assert!(Element::<u8>::from_str("[2-]").is_err());
+        assert!(Element::<u8>::from_str("[2").is_err());
+        // Gaps are at most as long as a match
+        let max = format!("[{}]", crate::MAX_MATCH_LEN);
+        assert!(Element::<u8>::from_str(&max).is_ok());
+        let longer = format!("[{}]", crate::MAX_MATCH_LEN + 1);
+        assert!(Element::<u8>::from_str(&longer).is_err());
+        assert!(Element::<u8>::from_str("[0-18446744073709551615]").is_err());
+    }
+
+    #[test]
+    fn display_gap() {
+        assert_eq!(Element::<u8>::Gap { min: 2, max: 8 }.to_string(), "[2-8]");
+        assert_eq!(Element::<u8>::Gap { min: 3, max: 3 }.to_string(), "[3]");
+    }
+
+    #[test]
+    fn string_conversion_range() {
+        let e = Element::<u8>::from_str("0x20-0x7e").unwrap();
+        assert_eq!(
+            e,
+            Element::Range {
+                min: 0x20,
+                max: 0x7e
+            }
+        );
+        assert_eq!(e.to_string(), "00100000-01111110");
+        assert!(Element::<u8>::from_str("0x7e-0x20").is_err());
+        assert!(Element::<u8>::from_str("0x2?-0x7e").is_err());
+        assert!(Element::<u8>::from_str("0x20-").is_err());
+    }
+
+    #[test]
+    fn matches_range() {
+        let e = Element::<u8>::Range {
+            min: 0x04,
+            max: 0x20,
+        };
+        assert!(!e.matches(0x03));
+        assert!(e.matches(0x04));
+        assert!(e.matches(0x20));
+        assert!(!e.matches(0x21));
+        assert!(!e.matches_any());
+        assert!(Element::<u8>::Range {
+            min: 0x00,
+            max: 0xff
+        }
+        .matches_any());
+    }
+
+    #[test]
+    fn string_conversion_repeat() {
+        assert_eq!(
+            Element::<u8>::from_str("0xff{16}").unwrap(),
+            Element::Repeat {
+                element: Box::new(Element::Byte(MaskedByte::new(0xff, 0xff))),
+                min: 16,
+                max: Some(16),
+                greedy: true,
+            }
+        );
+        for (string, display, range) in &[
+            ("0xff{16}", "11111111{16}", (16, Some(16))),
+            ("0x00{4,}", "00000000{4,}", (4, None)),
+            ("0x00{4,8}?", "00000000{4,8}?", (4, Some(8))),
+            ("0x??*", "????????*", (0, None)),
+            ("0x1?+?", "0001????+?", (1, None)),
+            ("(0x40|0x80){2}", "(01000000|10000000){2}", (2, Some(2))),
+            ("0x20-0x7e{3,}", "00100000-01111110{3,}", (3, None)),
+        ] {
+            let e = Element::<u8>::from_str(string).unwrap();
+            assert_eq!(e.to_string(), *display);
+            assert_eq!(e.len_range(), *range);
+        }
+        assert!(Element::<u8>::from_str("0x1?").is_ok());
+        assert!(Element::<u8>::from_str("0x00{8,4}").is_err());
+        assert!(Element::<u8>::from_str("0x00{a}").is_err());
+        assert!(Element::<u8>::from_str("[2]{3}").is_err());
+        assert!(Element::<u8>::from_str("0x00{2}{3}").is_err());
+        // Counts are at most as long as a match
+        let max = format!("0x00{{{},}}", crate::MAX_MATCH_LEN);
+        assert!(Element::<u8>::from_str(&max).is_ok());
+        let longer = crate::MAX_MATCH_LEN + 1;
+        assert!(Element::<u8>::from_str(&format!("0x00{{{}}}", longer)).is_err());
+        assert!(Element::<u8>::from_str(&format!("0x00{{{},}}", longer)).is_err());
+        assert!(Element::<u8>::from_str(&format!("0x00{{2,{}}}", longer)).is_err());
+        assert!(Element::<u8>::from_str("0x??{18446744073709551615}").is_err());
+    }
+
+    #[test]
+    fn negation() {
+        let e = Element::<u8>::from_str("!0x4?").unwrap();
+        assert!(e.is_single());
+        assert!(e.matches(0x00));
+        assert!(!e.matches(0x41));
+        assert_eq!(e.to_string(), "!0100????");
+
+        let e = Element::<u8>::from_str("!(0x00|0xff){2,}").unwrap();
+        assert_eq!(e.to_string(), "!(00000000|11111111){2,}");
+        assert_eq!(e.len_range(), (2, None));
+
+        assert!(Element::<u8>::from_str("!0x20-0x7e").unwrap().matches(0x80));
+        assert!(Element::<u8>::from_str("![2]").is_err());
+        assert!(Element::<u8>::from_str("!").is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{Element, MaskedByte, PatternFormatter};
+use std::fmt;
+use std::ops;
+use std::str::FromStr;
+
+// Signatures as written by IDA and x64dbg, like "48 8B ?? ?? 05 ? E8". Bytes are
+// bare hex numbers, "?" and "??" match any byte and "4?" leaves a nibble undefined.
+
+// Parses a signature into one MaskedByte element per byte
+pub fn parse<T>(string: &str) -> crate::Result<Vec<Element<T>>>
+where
+    T: From<u8>
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    let digits = T::zero().count_zeros() as usize / 4;
+
+    string
+        .split_whitespace()
+        .map(|token| {
+            if token == "?" {
+                return Ok(Element::Byte(MaskedByte::default()));
+            }
+
+            let invalid = || crate::Error::InvalidSignature {
+                string: token.to_string(),
+            };
+            if token.len() != digits || !token.chars().all(|c| c == '?' || c.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            MaskedByte::from_str(&format!("0x{}", token))
+                .map(Element::Byte)
+                .map_err(|_| invalid())
+        })
+        .collect()
+}
+
+// Writes bytes as signature, undefined nibbles become "?". Only masks undefining
+// whole nibbles can be written. Bytes come with the index of the element they
+// belong to, which errors report.
+pub fn format<T>(bytes: &[(usize, MaskedByte<T>)]) -> crate::Result<String>
+where
+    T: From<u8>
+        + fmt::Binary
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    let digits = T::zero().count_zeros() / 4;
+    let nibble = <T as From<u8>>::from(0xf);
+
+    let tokens = bytes
+        .iter()
+        .map(|(index, byte)| {
+            (0..digits)
+                .rev()
+                .map(|digit| {
+                    let shift = (digit * 4) as usize;
+                    let mask = (byte.mask() >> shift) & nibble;
+                    let value = (byte.value() >> shift) & nibble;
+                    if mask.is_zero() {
+                        Ok('?')
+                    } else if mask == nibble {
+                        Ok(std::char::from_digit(value.to_u32().unwrap(), 16)
+                            .unwrap()
+                            .to_ascii_uppercase())
+                    } else {
+                        Err(crate::Error::FormatUnsupported {
+                            element: PatternFormatter::new().masked_byte(byte),
+                            index: *index,
+                        })
+                    }
+                })
+                .collect::<crate::Result<String>>()
+        })
+        .collect::<crate::Result<Vec<String>>>()?;
+    Ok(tokens.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_signature() {
+        let elements = parse::<u8>("48 8B ?? ? 4? ?f").unwrap();
+        assert_eq!(
+            elements,
+            vec![
+                Element::Byte(MaskedByte::new(0x48, 0xff)),
+                Element::Byte(MaskedByte::new(0x8b, 0xff)),
+                Element::Byte(MaskedByte::new(0x00, 0x00)),
+                Element::Byte(MaskedByte::new(0x00, 0x00)),
+                Element::Byte(MaskedByte::new(0x40, 0xf0)),
+                Element::Byte(MaskedByte::new(0x0f, 0x0f)),
+            ]
+        );
+
+        assert!(parse::<u8>("").unwrap().is_empty());
+        assert!(parse::<u8>("48 8").is_err());
+        assert!(parse::<u8>("0x48").is_err());
+        assert!(parse::<u8>("4G").is_err());
+        assert!(parse::<u16>("488B ??").is_err());
+        assert_eq!(parse::<u16>("488B ????").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn format_signature() {
+        let bytes = [
+            (0, MaskedByte::<u8>::new(0x48, 0xff)),
+            (1, MaskedByte::new(0x00, 0x00)),
+            (1, MaskedByte::new(0x40, 0xf0)),
+        ];
+        assert_eq!(format(&bytes).unwrap(), "48 ?? 4?");
+        assert_eq!(
+            format(&[(2, MaskedByte::<u8>::new(0x40, 0xfe))])
+                .unwrap_err()
+                .to_string(),
+            "Element 0b0100000? at index 2 can not be written in this format"
+        );
+    }
+}
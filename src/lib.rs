@@ -1,30 +1,58 @@
+mod alternatives;
+mod captures;
+mod code;
+mod compiled_pattern;
+mod element;
+mod ida;
+mod literal;
 mod masked_byte;
+mod matcher;
 mod opt_fifo;
+mod parser;
 mod pattern;
+mod pattern_find_iterator;
+mod pattern_formatter;
+mod pattern_ref;
 mod pattern_replace_iterator;
 mod pattern_replace_result_iterator;
 mod pattern_search_iterator;
 mod pattern_search_ref_iterator;
 mod pattern_search_result_iterator;
+mod pattern_set;
 mod pattern_skip_iterator;
 mod pattern_skip_result_iterator;
 mod pattern_take_iterator;
 mod pattern_take_result_iterator;
+mod position;
 mod replacer;
 mod searcher;
 mod skipper;
 mod taker;
+#[cfg(test)]
+mod testing;
+mod typed;
+mod yara;
+mod yara_rule;
 
 pub mod prelude;
 
 use crate::opt_fifo::OptFifo;
+use crate::position::Position;
 use crate::replacer::Replacer;
 use crate::searcher::Searcher;
 use crate::skipper::Skipper;
 use crate::taker::Taker;
 
+pub use crate::alternatives::Alternatives;
+pub use crate::captures::Captures;
+pub use crate::compiled_pattern::CompiledPattern;
+pub use crate::element::Element;
+pub use crate::literal::Literal;
 pub use crate::masked_byte::MaskedByte;
 pub use crate::pattern::Pattern;
+pub use crate::pattern_find_iterator::PatternFindIterator;
+pub use crate::pattern_formatter::{PatternFormatter, Radix};
+pub use crate::pattern_ref::PatternRef;
 pub use crate::pattern_replace_iterator::{PatternReplaceExt, PatternReplaceIterator};
 pub use crate::pattern_replace_result_iterator::{
     PatternReplaceResultExt, PatternReplaceResultIterator,
@@ -34,10 +62,14 @@ pub use crate::pattern_search_ref_iterator::{PatternSearchRefExt, PatternSearchR
 pub use crate::pattern_search_result_iterator::{
     PatternSearchResultExt, PatternSearchResultIterator,
 };
+pub use crate::pattern_set::PatternSet;
 pub use crate::pattern_skip_iterator::{PatternSkipExt, PatternSkipIterator};
 pub use crate::pattern_skip_result_iterator::{PatternSkipResultExt, PatternSkipResultIterator};
 pub use crate::pattern_take_iterator::{PatternTakeExt, PatternTakeIterator};
 pub use crate::pattern_take_result_iterator::{PatternTakeResultExt, PatternTakeResultIterator};
+pub use crate::searcher::MAX_MATCH_LEN;
+pub use crate::typed::Typed;
+pub use crate::yara_rule::{YaraExport, YaraRule};
 
 use snafu::Snafu;
 
@@ -49,9 +81,75 @@ pub enum Error {
         source: crate::masked_byte::Error,
     },
 
+    #[snafu(display("Invalid alternatives {}", string))]
+    InvalidAlternatives { string: String },
+
+    #[snafu(display("Invalid assertion in {}", string))]
+    InvalidAssertion { string: String },
+
+    #[snafu(display("Invalid gap {}", string))]
+    InvalidGap { string: String },
+
+    #[snafu(display("Invalid group in {}", string))]
+    InvalidGroup { string: String },
+
+    #[snafu(display("Invalid identifier {}", string))]
+    InvalidIdentifier { string: String },
+
+    #[snafu(display("Invalid literal {}", string))]
+    InvalidLiteral { string: String },
+
+    #[snafu(display("Invalid negation {}", string))]
+    InvalidNegation { string: String },
+
+    #[snafu(display("Invalid quantifier {}", string))]
+    InvalidQuantifier { string: String },
+
+    #[snafu(display("Invalid range {}", string))]
+    InvalidRange { string: String },
+
+    #[snafu(display("Invalid separator {:?}", string))]
+    InvalidSeparator { string: String },
+
+    #[snafu(display("Invalid signature byte {}", string))]
+    InvalidSignature { string: String },
+
+    #[snafu(display("Invalid typed value {}", string))]
+    InvalidTyped { string: String },
+
+    #[snafu(display("Invalid YARA hex string {}", string))]
+    InvalidYara { string: String },
+
+    #[snafu(display("Line {} column {}: {}", line, column, source))]
+    Location {
+        line: usize,
+        column: usize,
+        source: Box<Error>,
+    },
+
+    #[snafu(display("Could not read pattern: {}", source))]
+    ReadError { source: std::io::Error },
+
+    #[snafu(display(
+        "Element {} at index {} can not be written in this format",
+        element,
+        index
+    ))]
+    FormatUnsupported { element: String, index: usize },
+
     #[snafu(display("Overhanging replace pattern is not fully defined"))]
     ReplaceNotDefined,
 
+    #[snafu(display(
+        "Element {} at index {} is not supported in a replace pattern",
+        element,
+        index
+    ))]
+    ReplaceUnsupported { element: String, index: usize },
+
+    #[snafu(display("YARA construct {} is not supported", string))]
+    YaraUnsupported { string: String },
+
     #[snafu(display(""))]
     IteratorError { source: Box<dyn std::error::Error> },
 }
@@ -60,6 +158,17 @@ pub type Result<T, E = crate::Error> = std::result::Result<T, E>;
 
 #[derive(PartialEq, Debug)]
 pub enum PatternSearchType<T> {
-    Match { data: Vec<T>, index: usize },
+    /// Matched data starting at stream offset index. The id identifies the Pattern
+    /// of a PatternSet that matched and is 0 when searching a single Pattern.
+    /// Mismatches holds the positions in data not matching the Pattern, which
+    /// only happens in an approximate search. Captures holds the parts of data
+    /// matched by the groups of the Pattern.
+    Match {
+        data: Vec<T>,
+        index: usize,
+        id: usize,
+        mismatches: Vec<usize>,
+        captures: Captures,
+    },
     NonMatch(T),
 }
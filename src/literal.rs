@@ -0,0 +1,261 @@
+use crate::{Element, MaskedByte};
+use std::fmt;
+use std::ops;
+use std::str;
+
+/// Literal is a quoted string like "Error %d\n" matching its bytes, or with "u"
+/// prefix like u"Text" matching its UTF-16LE encoding. C-style escapes are
+/// supported, "\xNN" adds a single byte (or code unit) and "\uNNNN" a character.
+/// Characters outside the BMP can also be given as surrogate pair "\ud83d\ude00".
+#[derive(PartialEq, Clone)]
+pub struct Literal<T> {
+    // Bytes or UTF-16 code units
+    units: Vec<u16>,
+    wide: bool,
+
+    // One fully defined MaskedByte element per byte in memory order
+    elements: Vec<Element<T>>,
+}
+
+impl<T> fmt::Debug for Literal<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Literal {{ {} }}", self)
+    }
+}
+
+impl<T> fmt::Display for Literal<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.wide {
+            write!(f, "u")?;
+        }
+        write!(f, "\"")?;
+        // Wide literals are decoded so characters outside the BMP are written as
+        // one character instead of a surrogate pair
+        let units: Vec<(u16, Option<char>)> = if self.wide {
+            let mut units = Vec::new();
+            for decoded in char::decode_utf16(self.units.iter().copied()) {
+                match decoded {
+                    Ok(c) if c.len_utf16() == 2 => units.push((0, Some(c))),
+                    Ok(c) => units.push((c as u16, Some(c))),
+                    Err(error) => units.push((error.unpaired_surrogate(), None)),
+                }
+            }
+            units
+        } else {
+            self.units
+                .iter()
+                .map(|unit| (*unit, char::from_u32(u32::from(*unit))))
+                .collect()
+        };
+
+        for unit in units {
+            match unit {
+                (_, Some('"')) => write!(f, "\\\"")?,
+                (_, Some('\\')) => write!(f, "\\\\")?,
+                (_, Some('\n')) => write!(f, "\\n")?,
+                (_, Some('\r')) => write!(f, "\\r")?,
+                (_, Some('\t')) => write!(f, "\\t")?,
+                (_, Some('\0')) => write!(f, "\\0")?,
+                (_, Some(c)) if c.is_ascii_graphic() || c == ' ' => write!(f, "{}", c)?,
+                (_, Some(c)) if self.wide && !c.is_control() => write!(f, "{}", c)?,
+                (unit, _) if self.wide => write!(f, "\\u{:04x}", unit)?,
+                (unit, _) => write!(f, "\\x{:02x}", unit)?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+// Parses exactly count hex digits from chars
+fn hex(chars: &mut str::Chars, count: usize) -> Option<u32> {
+    let digits: String = chars.take(count).collect();
+    if digits.len() != count {
+        return None;
+    }
+    u32::from_str_radix(&digits, 16).ok()
+}
+
+impl<T> Literal<T>
+where
+    T: From<u8>
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    fn new(units: Vec<u16>, wide: bool) -> Self {
+        let byte = |value: u8| Element::Byte(MaskedByte::from(<T as From<u8>>::from(value)));
+        let elements = units
+            .iter()
+            .flat_map(|unit| {
+                if wide {
+                    let [low, high] = unit.to_le_bytes();
+                    vec![byte(low), byte(high)]
+                } else {
+                    vec![byte(*unit as u8)]
+                }
+            })
+            .collect();
+
+        Self {
+            units,
+            wide,
+            elements,
+        }
+    }
+
+    /// Returns the bytes in memory order
+    pub fn elements(&self) -> &[Element<T>] {
+        &self.elements
+    }
+}
+
+impl<T> str::FromStr for Literal<T>
+where
+    T: From<u8>
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    type Err = crate::Error;
+
+    /// Creates Literal from a quoted string, optionally prefixed with "u". Only
+    /// patterns of bytes support literals.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let invalid = || crate::Error::InvalidLiteral {
+            string: string.to_string(),
+        };
+
+        if T::zero().count_zeros() != 8 {
+            return Err(invalid());
+        }
+
+        let (wide, quoted) = match string.strip_prefix('u') {
+            Some(quoted) => (true, quoted),
+            None => (false, string),
+        };
+        let inner = quoted
+            .strip_prefix('"')
+            .and_then(|inner| inner.strip_suffix('"'))
+            .ok_or_else(invalid)?;
+
+        let mut units = Vec::new();
+        let push = |c: char, units: &mut Vec<u16>| {
+            if wide {
+                units.extend(c.encode_utf16(&mut [0; 2]).iter());
+            } else {
+                units.extend(c.to_string().bytes().map(u16::from));
+            }
+        };
+
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c == '"' {
+                return Err(invalid());
+            }
+            if c != '\\' {
+                push(c, &mut units);
+                continue;
+            }
+
+            let escaped = match chars.next().ok_or_else(invalid)? {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                '0' => '\0',
+                'a' => '\x07',
+                'b' => '\x08',
+                'f' => '\x0c',
+                'v' => '\x0b',
+                c @ ('\\' | '"' | '\'') => c,
+                'x' => {
+                    // A single byte or code unit, not necessarily a valid character
+                    units.push(hex(&mut chars, 2).ok_or_else(invalid)? as u16);
+                    continue;
+                }
+                'u' => {
+                    let unit = hex(&mut chars, 4).ok_or_else(invalid)?;
+
+                    // Characters outside the BMP may be given as surrogate pair
+                    let unit = if (0xd800..0xdc00).contains(&unit) {
+                        let low = match (chars.next(), chars.next()) {
+                            (Some('\\'), Some('u')) => hex(&mut chars, 4),
+                            _ => None,
+                        }
+                        .filter(|low| (0xdc00..0xe000).contains(low))
+                        .ok_or_else(invalid)?;
+                        0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00)
+                    } else {
+                        unit
+                    };
+                    char::from_u32(unit).ok_or_else(invalid)?
+                }
+                _ => return Err(invalid()),
+            };
+            push(escaped, &mut units);
+        }
+
+        Ok(Self::new(units, wide))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn bytes(literal: &Literal<u8>) -> Vec<u8> {
+        literal
+            .elements()
+            .iter()
+            .map(|element| element.defined().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn expand() {
+        let l = Literal::<u8>::from_str(r#""Error %d\n""#).unwrap();
+        assert_eq!(bytes(&l), b"Error %d\n");
+
+        let l = Literal::<u8>::from_str(r#""\x00\xff\"\\ü""#).unwrap();
+        assert_eq!(bytes(&l), [0x00, 0xff, b'"', b'\\', 0xc3, 0xbc]);
+
+        let l = Literal::<u8>::from_str(r#"u"Teü""#).unwrap();
+        assert_eq!(bytes(&l), [b'T', 0, b'e', 0, 0xfc, 0]);
+    }
+
+    #[test]
+    fn string_conversion() {
+        for (string, display) in &[
+            (r#""Error %d\n""#, r#""Error %d\n""#),
+            (r#""a\"b\\\x01ü""#, r#""a\"b\\\x01\xc3\xbc""#),
+            (r#"u"Tü\x01""#, r#"u"Tü\u0001""#),
+            (r#""""#, r#""""#),
+            (r#"u"😀""#, r#"u"😀""#),
+            (r#"u"\ud83d\ude00""#, r#"u"😀""#),
+        ] {
+            assert_eq!(
+                Literal::<u8>::from_str(string).unwrap().to_string(),
+                *display
+            );
+        }
+
+        assert!(Literal::<u8>::from_str(r#""abc"#).is_err());
+        assert!(Literal::<u8>::from_str(r#""a"b""#).is_err());
+        assert!(Literal::<u8>::from_str(r#""\q""#).is_err());
+        assert!(Literal::<u8>::from_str(r#""\x1""#).is_err());
+        assert!(Literal::<u8>::from_str(r#""\ud800""#).is_err());
+        assert!(Literal::<u8>::from_str(r#""\ud800\u0041""#).is_err());
+        assert!(Literal::<u8>::from_str(r#""\ude00""#).is_err());
+        assert!(Literal::<u16>::from_str(r#""abc""#).is_err());
+    }
+}
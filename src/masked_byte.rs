@@ -5,7 +5,7 @@ use std::fmt;
 use std::ops;
 use std::str;
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub struct MaskedByte<T> {
     // No assumptions are made for bits where according mask bit is 0.
     value: T,
@@ -94,6 +94,14 @@ where
         Self { value, mask }
     }
 
+    pub fn value(&self) -> T {
+        self.value
+    }
+
+    pub fn mask(&self) -> T {
+        self.mask
+    }
+
     /// Matches data against MaskedByte.
     /// Where mask bit is 1 the bit of value has to fit data, mask bit 0 means
     /// data bit can be anything. Returns true if masked bits match.
@@ -101,6 +109,11 @@ where
         data & self.mask == self.value & self.mask
     }
 
+    /// Returns true if all bits are undefined so any data matches
+    pub fn matches_any(&self) -> bool {
+        self.mask.is_zero()
+    }
+
     /// Sets data bits to value bits where masked bits are 1
     pub fn set(&self, data: T) -> T {
         (data & !self.mask) | (self.value & self.mask)
@@ -183,10 +196,10 @@ where
     /// 0x?5 equals 0b????0101 which represents a value of 0b00000101 with a mask
     /// of 0b00001111.
     fn from_str(string: &str) -> Result<Self, Self::Err> {
-        let bits = match &string[..2] {
-            "0b" => 1,
-            "0x" => 4,
-            "0o" => 3,
+        let bits = match string.get(..2) {
+            Some("0b") => 1,
+            Some("0x") => 4,
+            Some("0o") => 3,
             _ => {
                 return Err(Error::UnknownPrefix).context(ParseError {
                     string: string.to_string(),
@@ -199,6 +212,19 @@ where
     }
 }
 
+impl<T> From<T> for MaskedByte<T>
+where
+    T: num::PrimInt,
+{
+    /// Creates a fully defined MaskedByte
+    fn from(value: T) -> Self {
+        Self {
+            value,
+            mask: T::max_value(),
+        }
+    }
+}
+
 impl<T> Default for MaskedByte<T>
 where
     T: Default,
@@ -257,6 +283,12 @@ mod tests {
         assert_eq!(h.unwrap(), MaskedByte::<u8>::new(0xff, 0xff));
     }
 
+    #[test]
+    fn too_short() {
+        assert!(MaskedByte::<u8>::from_str("").is_err());
+        assert!(MaskedByte::<u8>::from_str("0").is_err());
+    }
+
     #[test]
     fn too_big() {
         let h = MaskedByte::<u8>::from_str("0o777");
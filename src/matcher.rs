@@ -0,0 +1,540 @@
+use crate::element::sequence_len;
+use crate::Element;
+use std::cell::Cell;
+use std::fmt;
+use std::ops;
+use std::ops::Range;
+
+// Outcome of matching a list of elements against data at some origin
+#[derive(PartialEq, Debug)]
+pub enum Outcome {
+    // Elements match len bytes of data from origin. Mismatches and captures are
+    // relative to origin. Captures holds the ranges of the groups by index, it is
+    // shorter if the last groups did not take part.
+    Match {
+        len: usize,
+        mismatches: Vec<usize>,
+        captures: Vec<Option<Range<usize>>>,
+    },
+
+    // More data is needed to decide
+    Partial,
+
+    // Elements do not match at origin
+    Fail,
+}
+
+// Greedy repeat a Partial outcome waits on and count of bytes it may still take.
+// Matching again gives Partial as long as further bytes continue the run.
+#[derive(Clone, Copy)]
+pub struct OpenRun<'a, T> {
+    pub element: &'a Element<T>,
+    pub remaining: usize,
+}
+
+// State of the path currently tried
+#[derive(Default)]
+struct State {
+    mismatches: Vec<usize>,
+    captures: Vec<Option<Range<usize>>>,
+}
+
+// What to match after the elements of a group or sequence are matched
+struct Next<'n, 'a, T> {
+    // Elements containing the group and index of the element after it
+    elements: &'a [Element<T>],
+    index: usize,
+
+    // Index of the group if it captures and position it started at
+    group: Option<usize>,
+    start: usize,
+
+    parent: Option<&'n Next<'n, 'a, T>>,
+}
+
+// Backtracking matcher. Alternatives are tried in order of preference, the first
+// one matching wins. If an alternative needs more data than available, the
+// outcome is Partial even if a less preferred alternative would match.
+pub struct Matcher<'a, 'b, T> {
+    elements: &'a [Element<T>],
+    data: &'b [T],
+
+    // Position the match starts at, bytes before it are seen by lookbehind only
+    origin: usize,
+
+    // Data begins at the start of input
+    start: bool,
+
+    // Data is complete, no more bytes follow
+    end: bool,
+
+    // Count of single byte elements allowed to not match their byte. Repeated
+    // elements always need to match.
+    budget: usize,
+
+    // A match needs to span all of data, used for lookbehind
+    exact: bool,
+
+    // Set if the outcome is Partial because of an open greedy repeat
+    open: Cell<Option<OpenRun<'a, T>>>,
+}
+
+impl<'a, 'b, T> Matcher<'a, 'b, T>
+where
+    T: From<u8>
+        + fmt::Binary
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + ops::ShlAssign<u32>
+        + PartialEq
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>
+        + ops::BitOrAssign
+        + ops::BitAndAssign,
+{
+    pub fn new(
+        elements: &'a [Element<T>],
+        data: &'b [T],
+        origin: usize,
+        start: bool,
+        end: bool,
+        budget: usize,
+    ) -> Self {
+        Self {
+            elements,
+            data,
+            origin,
+            start,
+            end,
+            budget,
+            exact: false,
+            open: Cell::new(None),
+        }
+    }
+
+    pub fn run(&self) -> Outcome {
+        let mut state = State::default();
+        self.match_from(self.elements, 0, self.origin, &mut state, None)
+    }
+
+    // Greedy repeat the last Partial outcome of run waits on, if any
+    pub fn open(&self) -> Option<OpenRun<'a, T>> {
+        self.open.get()
+    }
+
+    // Outcome of elements of a lookahead assertion at position, None if more data
+    // is needed
+    fn ahead(&self, elements: &[Element<T>], position: usize) -> Option<bool> {
+        match Matcher::new(elements, self.data, position, self.start, self.end, 0).run() {
+            Outcome::Match { .. } => Some(true),
+            Outcome::Fail => Some(false),
+            Outcome::Partial => None,
+        }
+    }
+
+    // True if elements of a lookbehind assertion match bytes ending at position
+    fn behind(&self, elements: &[Element<T>], position: usize) -> bool {
+        let (min, max) = sequence_len(elements);
+        (min..=max.unwrap_or(position).min(position)).any(|len| {
+            let matcher = Matcher {
+                exact: true,
+                ..Matcher::new(
+                    elements,
+                    &self.data[..position],
+                    position - len,
+                    self.start,
+                    true,
+                    0,
+                )
+            };
+            matches!(matcher.run(), Outcome::Match { .. })
+        })
+    }
+
+    // Outcome if position is beyond available data
+    fn beyond(&self) -> Outcome {
+        if self.end {
+            Outcome::Fail
+        } else {
+            Outcome::Partial
+        }
+    }
+
+    // Matches elements starting at index against data starting at position. At the
+    // end of elements matching continues with next.
+    fn match_from(
+        &self,
+        elements: &'a [Element<T>],
+        index: usize,
+        position: usize,
+        state: &mut State,
+        next: Option<&Next<'_, 'a, T>>,
+    ) -> Outcome {
+        let element = match elements.get(index) {
+            Some(element) => element,
+            None => return self.match_next(position, state, next),
+        };
+
+        match element {
+            Element::Byte(_)
+            | Element::Alternatives(_)
+            | Element::Range { .. }
+            | Element::Not(_) => {
+                let byte = match self.data.get(position) {
+                    Some(byte) => *byte,
+                    None => return self.beyond(),
+                };
+
+                if element.matches(byte) {
+                    self.match_from(elements, index + 1, position + 1, state, next)
+                } else if state.mismatches.len() < self.budget {
+                    state.mismatches.push(position - self.origin);
+                    let outcome = self.match_from(elements, index + 1, position + 1, state, next);
+                    state.mismatches.pop();
+                    outcome
+                } else {
+                    Outcome::Fail
+                }
+            }
+            Element::Start => {
+                if self.start && position == 0 {
+                    self.match_from(elements, index + 1, position, state, next)
+                } else {
+                    Outcome::Fail
+                }
+            }
+            Element::End => {
+                if position < self.data.len() {
+                    Outcome::Fail
+                } else if self.end {
+                    self.match_from(elements, index + 1, position, state, next)
+                } else {
+                    Outcome::Partial
+                }
+            }
+            Element::Gap { min, max } => {
+                for count in *min..=*max {
+                    if position + count > self.data.len() {
+                        return self.beyond();
+                    }
+                    match self.match_from(elements, index + 1, position + count, state, next) {
+                        Outcome::Fail => {}
+                        outcome => return outcome,
+                    }
+                }
+                Outcome::Fail
+            }
+            Element::Repeat {
+                element,
+                min,
+                max,
+                greedy,
+            } => {
+                let limit = max.unwrap_or(usize::MAX);
+                let run = self.data[position..]
+                    .iter()
+                    .take(limit)
+                    .take_while(|byte| element.matches(**byte))
+                    .count();
+
+                // More data might continue the run
+                let open = !self.end && position + run == self.data.len() && run < limit;
+
+                if *greedy {
+                    if open {
+                        self.open.set(Some(OpenRun {
+                            element,
+                            remaining: limit - run,
+                        }));
+                        return Outcome::Partial;
+                    }
+                    for count in (*min..=run).rev() {
+                        match self.match_from(elements, index + 1, position + count, state, next) {
+                            Outcome::Fail => {}
+                            outcome => return outcome,
+                        }
+                    }
+                } else {
+                    for count in *min..=run {
+                        match self.match_from(elements, index + 1, position + count, state, next) {
+                            Outcome::Fail => {}
+                            outcome => return outcome,
+                        }
+                    }
+                    if open {
+                        return Outcome::Partial;
+                    }
+                }
+                Outcome::Fail
+            }
+            Element::Group {
+                index: group,
+                elements: inner,
+                ..
+            } => {
+                let after = Next {
+                    elements,
+                    index: index + 1,
+                    group: Some(*group),
+                    start: position,
+                    parent: next,
+                };
+                self.match_from(inner, 0, position, state, Some(&after))
+            }
+            Element::Assertion {
+                behind,
+                negative,
+                elements: inner,
+            } => {
+                let found = if *behind {
+                    self.behind(inner, position)
+                } else {
+                    match self.ahead(inner, position) {
+                        Some(found) => found,
+                        None => return Outcome::Partial,
+                    }
+                };
+                if found != *negative {
+                    self.match_from(elements, index + 1, position, state, next)
+                } else {
+                    Outcome::Fail
+                }
+            }
+            Element::Typed(_) | Element::Literal(_) => {
+                let after = Next {
+                    elements,
+                    index: index + 1,
+                    group: None,
+                    start: position,
+                    parent: next,
+                };
+                let sequence = element.sequence().unwrap_or_default();
+                self.match_from(sequence, 0, position, state, Some(&after))
+            }
+        }
+    }
+
+    // Closes the innermost open group and continues after it, or finishes the match
+    fn match_next(
+        &self,
+        position: usize,
+        state: &mut State,
+        next: Option<&Next<'_, 'a, T>>,
+    ) -> Outcome {
+        let next = match next {
+            Some(next) => next,
+            None if self.exact && position < self.data.len() => return Outcome::Fail,
+            None => {
+                return Outcome::Match {
+                    len: position - self.origin,
+                    mismatches: state.mismatches.clone(),
+                    captures: state.captures.clone(),
+                }
+            }
+        };
+
+        let group = match next.group {
+            Some(group) => group,
+            None => {
+                return self.match_from(next.elements, next.index, position, state, next.parent)
+            }
+        };
+
+        if state.captures.len() <= group {
+            state.captures.resize(group + 1, None);
+        }
+        let previous =
+            state.captures[group].replace(next.start - self.origin..position - self.origin);
+        let outcome = self.match_from(next.elements, next.index, position, state, next.parent);
+        state.captures[group] = previous;
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pattern;
+    use std::str::FromStr;
+
+    fn run(pattern: &str, data: &[u8], end: bool) -> Outcome {
+        let pattern = Pattern::<u8>::from_str(pattern).unwrap();
+        pattern.match_at(data, 0, true, end, 0)
+    }
+
+    #[test]
+    fn gap_shortest() {
+        assert_eq!(
+            run("0x01 [1-3] 0x02", &[0x01, 0x02, 0x02, 0x02], false),
+            Outcome::Match {
+                len: 3,
+                mismatches: vec![],
+                captures: vec![]
+            }
+        );
+    }
+
+    #[test]
+    fn gap_partial() {
+        assert_eq!(
+            run("0x01 [1-3] 0x02", &[0x01, 0x03, 0x03], false),
+            Outcome::Partial
+        );
+        assert_eq!(
+            run("0x01 [1-3] 0x02", &[0x01, 0x03, 0x03], true),
+            Outcome::Fail
+        );
+        assert_eq!(
+            run("0x01 [1-3] 0x02", &[0x01, 0x03, 0x03, 0x03, 0x03], false),
+            Outcome::Fail
+        );
+    }
+
+    #[test]
+    fn repeat_greedy() {
+        assert_eq!(run("0x00* 0x??", &[0x00, 0x00], false), Outcome::Partial);
+        assert_eq!(
+            run("0x00* 0x??", &[0x00, 0x00, 0x01], false),
+            Outcome::Match {
+                len: 3,
+                mismatches: vec![],
+                captures: vec![]
+            }
+        );
+        assert_eq!(
+            run("0x00* 0x??", &[0x00, 0x00], true),
+            Outcome::Match {
+                len: 2,
+                mismatches: vec![],
+                captures: vec![]
+            }
+        );
+        assert_eq!(
+            run("0x00{1,2} 0x00", &[0x00, 0x00, 0x00], false),
+            Outcome::Match {
+                len: 3,
+                mismatches: vec![],
+                captures: vec![]
+            }
+        );
+    }
+
+    #[test]
+    fn repeat_lazy() {
+        assert_eq!(
+            run("0x00*? 0x??", &[0x00, 0x00], false),
+            Outcome::Match {
+                len: 1,
+                mismatches: vec![],
+                captures: vec![]
+            }
+        );
+        assert_eq!(
+            run("0x00{2,}? 0x01", &[0x00, 0x00, 0x00], false),
+            Outcome::Partial
+        );
+        assert_eq!(
+            run("0x00{2,}? 0x01", &[0x00, 0x00, 0x00], true),
+            Outcome::Fail
+        );
+        assert_eq!(run("0x00{2,}? 0x01", &[0x00, 0x02], false), Outcome::Fail);
+    }
+
+    #[test]
+    fn group_captures() {
+        assert_eq!(
+            run(
+                "0x01 (?<a> 0x?? ( 0x02* ) ) 0x03",
+                &[0x01, 0x04, 0x02, 0x02, 0x03],
+                false
+            ),
+            Outcome::Match {
+                len: 5,
+                mismatches: vec![],
+                captures: vec![Some(1..4), Some(2..4)]
+            }
+        );
+
+        // Captures of failed paths are dropped
+        assert_eq!(
+            run("( 0x01 [0-2] ) 0x02", &[0x01, 0x03, 0x02], false),
+            Outcome::Match {
+                len: 3,
+                mismatches: vec![],
+                captures: vec![Some(0..2)]
+            }
+        );
+    }
+
+    #[test]
+    fn anchors() {
+        let pattern = Pattern::<u8>::from_str("^ 0x01").unwrap();
+        assert!(matches!(
+            pattern.match_at(&[0x01], 0, true, false, 0),
+            Outcome::Match { len: 1, .. }
+        ));
+        assert_eq!(pattern.match_at(&[0x01], 0, false, false, 0), Outcome::Fail);
+
+        assert_eq!(run("0x01 $", &[0x01], false), Outcome::Partial);
+        assert_eq!(run("0x01 $", &[0x01, 0x02], false), Outcome::Fail);
+        assert!(matches!(
+            run("0x01 $", &[0x01], true),
+            Outcome::Match { len: 1, .. }
+        ));
+        assert!(matches!(
+            run("0x01 [0-2] $", &[0x01, 0x02], true),
+            Outcome::Match { len: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn lookahead() {
+        assert_eq!(
+            run("0x01 (?= 0x02 0x03 )", &[0x01, 0x02, 0x03], false),
+            Outcome::Match {
+                len: 1,
+                mismatches: vec![],
+                captures: vec![]
+            }
+        );
+        assert_eq!(
+            run("0x01 (?= 0x02 0x03 )", &[0x01, 0x02], false),
+            Outcome::Partial
+        );
+        assert_eq!(
+            run("0x01 (?= 0x02 0x03 )", &[0x01, 0x02], true),
+            Outcome::Fail
+        );
+        assert!(matches!(
+            run("0x01 (?! 0x02 )", &[0x01, 0x03], false),
+            Outcome::Match { len: 1, .. }
+        ));
+        assert_eq!(run("0x01 (?! 0x02 )", &[0x01, 0x02], false), Outcome::Fail);
+    }
+
+    #[test]
+    fn lookbehind() {
+        let pattern = Pattern::<u8>::from_str("(?<= 0x01 [0-1] ) 0x02").unwrap();
+        let outcome = |data: &[u8], origin| pattern.match_at(data, origin, true, false, 0);
+        assert!(matches!(
+            outcome(&[0x01, 0x02], 1),
+            Outcome::Match { len: 1, .. }
+        ));
+        assert!(matches!(
+            outcome(&[0x01, 0x05, 0x02], 2),
+            Outcome::Match { len: 1, .. }
+        ));
+        assert_eq!(outcome(&[0x05, 0x02], 1), Outcome::Fail);
+        assert_eq!(outcome(&[0x02], 0), Outcome::Fail);
+
+        let pattern = Pattern::<u8>::from_str("(?<! 0x01 ) 0x02").unwrap();
+        assert!(matches!(
+            pattern.match_at(&[0x02], 0, true, false, 0),
+            Outcome::Match { len: 1, .. }
+        ));
+        assert_eq!(
+            pattern.match_at(&[0x01, 0x02], 1, true, false, 0),
+            Outcome::Fail
+        );
+    }
+}
@@ -0,0 +1,468 @@
+use crate::element::sequence_len;
+use crate::Element;
+use std::ops;
+use std::str::FromStr;
+
+// Token of the pattern grammar
+#[derive(PartialEq, Debug)]
+enum Token {
+    // Single element like "0x41", "[2-4]", "(0x40|0x80){2}" or "\"a b\""
+    Element(String),
+
+    // Opening parenthesis of a group or assertion
+    Open(Open),
+
+    // Closing parenthesis of a group
+    Close,
+}
+
+// What an opening parenthesis starts
+#[derive(PartialEq, Debug)]
+enum Open {
+    // Group, optionally with a name like "(?<name>"
+    Group(Option<String>),
+
+    // Assertion like "(?=", "(?!", "(?<=" or "(?<!"
+    Assertion { behind: bool, negative: bool },
+}
+
+// Error at the char index it occurred
+pub type Located = (usize, crate::Error);
+
+fn invalid_group(string: &str, index: usize) -> Located {
+    (
+        index,
+        crate::Error::InvalidGroup {
+            string: string.to_string(),
+        },
+    )
+}
+
+// Splits string into tokens with the char index they start at. Elements are
+// separated by whitespace and parentheses, except for alternatives which are
+// enclosed in parentheses themselves and literals which are enclosed in quotes.
+// Groups and assertions start with "(" or "(?" and a marker.
+// Anchors "^" and "$" are tokens of their own. Comments starting with "#" or "//"
+// run to the end of the line, a backslash at the end of a line joins it with the
+// next one, even if followed by trailing spaces or tabs.
+fn tokenize(string: &str) -> Result<Vec<(usize, Token)>, Located> {
+    let chars: Vec<char> = string.chars().collect();
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut start = 0;
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        index += 1;
+
+        if token.is_empty() {
+            start = index - 1;
+        }
+
+        // Literals end at the next quote not escaped with a backslash
+        if c == '"' {
+            token.push(c);
+            loop {
+                let c = *chars.get(index).ok_or_else(|| {
+                    (
+                        start,
+                        crate::Error::InvalidLiteral {
+                            string: string.to_string(),
+                        },
+                    )
+                })?;
+                token.push(c);
+                index += 1;
+
+                match c {
+                    '"' => break,
+                    '\\' => {
+                        if let Some(c) = chars.get(index) {
+                            token.push(*c);
+                            index += 1;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            continue;
+        }
+
+        // Spaces and tabs between the backslash and the line break are ignored
+        if c == '\\' {
+            let blank = chars[index..]
+                .iter()
+                .take_while(|c| **c == ' ' || **c == '\t')
+                .count();
+            let skip = match &chars[index + blank..] {
+                ['\n', ..] => Some(1),
+                ['\r', '\n', ..] => Some(2),
+                _ => None,
+            };
+            if let Some(skip) = skip {
+                index += blank + skip;
+                continue;
+            }
+        }
+
+        // Anchors are tokens of their own
+        if c == '^' || c == '$' {
+            if !token.is_empty() {
+                tokens.push((start, Token::Element(std::mem::take(&mut token))));
+            }
+            tokens.push((index - 1, Token::Element(c.to_string())));
+            continue;
+        }
+
+        let comment = c == '#' || (c == '/' && chars.get(index) == Some(&'/'));
+        if c != '(' && c != ')' && !c.is_whitespace() && !comment {
+            token.push(c);
+            continue;
+        }
+
+        if c == '(' {
+            // Alternatives contain "|" and no further parentheses
+            let rest = &chars[index..];
+            if rest.first() == Some(&'?') {
+                // Group or assertion
+            } else if let Some(end) = rest.iter().position(|c| *c == '(' || *c == ')') {
+                if rest[end] == ')' && rest[..end].contains(&'|') {
+                    token.push(c);
+                    token.extend(&rest[..=end]);
+                    index += end + 1;
+                    continue;
+                }
+            }
+        }
+
+        if !token.is_empty() {
+            tokens.push((start, Token::Element(std::mem::take(&mut token))));
+        }
+
+        match c {
+            _ if comment => {
+                while index < chars.len() && chars[index] != '\n' {
+                    index += 1;
+                }
+            }
+            '(' if chars.get(index) == Some(&'?') => {
+                let assertion = match chars[index + 1..] {
+                    ['=', ..] => Some((false, false, 2)),
+                    ['!', ..] => Some((false, true, 2)),
+                    ['<', '=', ..] => Some((true, false, 3)),
+                    ['<', '!', ..] => Some((true, true, 3)),
+                    ['<', ..] => None,
+                    _ => return Err(invalid_group(string, index - 1)),
+                };
+                if let Some((behind, negative, len)) = assertion {
+                    tokens.push((index - 1, Token::Open(Open::Assertion { behind, negative })));
+                    index += len;
+                    continue;
+                }
+                let name: String = chars[index + 2..]
+                    .iter()
+                    .take_while(|c| **c != '>')
+                    .collect();
+                if name.is_empty()
+                    || !name.chars().all(|c| c.is_alphanumeric() || c == '_')
+                    || chars.get(index + 2 + name.chars().count()) != Some(&'>')
+                {
+                    return Err(invalid_group(string, index - 1));
+                }
+                tokens.push((index - 1, Token::Open(Open::Group(Some(name.clone())))));
+                index += 3 + name.chars().count();
+            }
+            '(' => tokens.push((index - 1, Token::Open(Open::Group(None)))),
+            ')' => tokens.push((index - 1, Token::Close)),
+            _ => {}
+        }
+    }
+
+    if !token.is_empty() {
+        tokens.push((start, Token::Element(token)));
+    }
+    Ok(tokens)
+}
+
+// Group whose closing parenthesis is not parsed yet
+struct OpenGroup<T> {
+    // Char index of the opening parenthesis
+    start: usize,
+    index: usize,
+    open: Open,
+    elements: Vec<Element<T>>,
+}
+
+// Parses string into a list of elements. Groups are numbered in order of their
+// opening parenthesis, group names need to be unique. Assertions need to match a
+// limited count of bytes, so searching only buffers a limited count of bytes.
+// Errors come with the char index they occurred at.
+pub fn parse<T>(string: &str) -> Result<Vec<Element<T>>, Located>
+where
+    T: From<u8>
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    // Groups currently open, the first entry is the top level
+    let mut stack = vec![OpenGroup {
+        start: 0,
+        index: 0,
+        open: Open::Group(None),
+        elements: Vec::new(),
+    }];
+    let mut groups = 0;
+    let mut names = Vec::new();
+
+    for (start, token) in tokenize(string)? {
+        match token {
+            Token::Element(token) => {
+                let element = Element::from_str(&token).map_err(|error| (start, error))?;
+                stack.last_mut().unwrap().elements.push(element);
+            }
+            Token::Open(open) => {
+                // Names need to be unique to look captures up by name
+                if let Open::Group(Some(name)) = &open {
+                    if names.contains(name) {
+                        return Err(invalid_group(string, start));
+                    }
+                    names.push(name.clone());
+                }
+                stack.push(OpenGroup {
+                    start,
+                    index: groups,
+                    open,
+                    elements: Vec::new(),
+                });
+                if let Some(OpenGroup {
+                    open: Open::Group(_),
+                    ..
+                }) = stack.last()
+                {
+                    groups += 1;
+                }
+            }
+            Token::Close => {
+                if stack.len() < 2 {
+                    return Err(invalid_group(string, start));
+                }
+                let OpenGroup {
+                    start,
+                    index,
+                    open,
+                    elements,
+                } = stack.pop().unwrap();
+                let element = match open {
+                    Open::Group(name) => Element::Group {
+                        index,
+                        name,
+                        elements,
+                    },
+                    Open::Assertion { behind, negative } => {
+                        if sequence_len(&elements).1.is_none() {
+                            return Err((
+                                start,
+                                crate::Error::InvalidAssertion {
+                                    string: string.to_string(),
+                                },
+                            ));
+                        }
+                        Element::Assertion {
+                            behind,
+                            negative,
+                            elements,
+                        }
+                    }
+                };
+                stack.last_mut().unwrap().elements.push(element);
+            }
+        }
+    }
+
+    if stack.len() > 1 {
+        return Err(invalid_group(string, stack.last().unwrap().start));
+    }
+    Ok(stack.pop().unwrap().elements)
+}
+
+// Converts a char index in string to line and column, both counting from 1
+pub fn location(string: &str, index: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut column = 1;
+    for c in string.chars().take(index) {
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+// Adds line and column to an error located in string
+pub fn locate(string: &str, (index, error): Located) -> crate::Error {
+    let (line, column) = location(string, index);
+    crate::Error::Location {
+        line,
+        column,
+        source: Box::new(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(string: &str) -> Token {
+        Token::Element(string.to_string())
+    }
+
+    fn tokens_of(string: &str) -> Vec<Token> {
+        tokenize(string)
+            .unwrap()
+            .into_iter()
+            .map(|(_, token)| token)
+            .collect()
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            tokens_of("0x01 (?<addr> 0x?? (0x40 | 0x80){2})(0x02 )"),
+            [
+                element("0x01"),
+                Token::Open(Open::Group(Some("addr".to_string()))),
+                element("0x??"),
+                element("(0x40 | 0x80){2}"),
+                Token::Close,
+                Token::Open(Open::Group(None)),
+                element("0x02"),
+                Token::Close,
+            ]
+        );
+        assert_eq!(
+            tokens_of(r#"0x01 u"a (b)\" c"(0x02)"#),
+            [
+                element("0x01"),
+                element(r#"u"a (b)\" c""#),
+                Token::Open(Open::Group(None)),
+                element("0x02"),
+                Token::Close,
+            ]
+        );
+        assert_eq!(
+            tokens_of("^0x01 \"$\"$"),
+            [
+                element("^"),
+                element("0x01"),
+                element("\"$\""),
+                element("$")
+            ]
+        );
+        assert!(tokenize(r#"0x01 "a b"#).is_err());
+        assert!(tokenize("(?<> 0x01)").is_err());
+        assert!(tokenize("(?<addr 0x01)").is_err());
+        assert!(tokenize("(?x 0x01)").is_err());
+    }
+
+    #[test]
+    fn assertions() {
+        assert_eq!(
+            tokens_of("(?=0x01)(?<! 0x02 )"),
+            [
+                Token::Open(Open::Assertion {
+                    behind: false,
+                    negative: false
+                }),
+                element("0x01"),
+                Token::Close,
+                Token::Open(Open::Assertion {
+                    behind: true,
+                    negative: true
+                }),
+                element("0x02"),
+                Token::Close,
+            ]
+        );
+
+        // Assertions take no group index
+        let elements = parse::<u8>("(?! ( 0x01 ) ) ( 0x02 )").unwrap();
+        assert!(matches!(
+            &elements[0],
+            Element::Assertion { elements, .. } if matches!(elements[0], Element::Group { index: 0, .. })
+        ));
+        assert!(matches!(elements[1], Element::Group { index: 1, .. }));
+
+        assert!(parse::<u8>("(?= 0x01+ )").is_err());
+        assert!(parse::<u8>("(?<= 0x01 [1-2] )").is_ok());
+    }
+
+    #[test]
+    fn groups() {
+        let elements = parse::<u8>("( 0x01 (?<b> 0x02 ) ) ( 0x03 )").unwrap();
+        assert_eq!(elements.len(), 2);
+        match &elements[0] {
+            Element::Group {
+                index: 0,
+                name: None,
+                elements,
+            } => assert_eq!(
+                elements[1],
+                Element::Group {
+                    index: 1,
+                    name: Some("b".to_string()),
+                    elements: vec![Element::from_str("0x02").unwrap()],
+                }
+            ),
+            other => panic!("Unexpected {:?}", other),
+        }
+        assert!(matches!(elements[1], Element::Group { index: 2, .. }));
+
+        assert!(parse::<u8>("( 0x01").is_err());
+        assert!(parse::<u8>("0x01 )").is_err());
+
+        let text = "(?<a> 0x01 ) (?<a> 0x02 )";
+        assert!(matches!(
+            parse::<u8>(text),
+            Err((13, crate::Error::InvalidGroup { .. }))
+        ));
+    }
+
+    #[test]
+    fn comments() {
+        let text = "# header\n0x01 0x02 // first\n\n0x0\\\n3 \"#//\"# end";
+        assert_eq!(
+            tokens_of(text),
+            [
+                element("0x01"),
+                element("0x02"),
+                element("0x03"),
+                element("\"#//\""),
+            ]
+        );
+
+        // Trailing blanks after the backslash still join the lines
+        assert_eq!(
+            tokens_of("0x0\\ \t\n3 0x0\\  \r\n4"),
+            [element("0x03"), element("0x04")]
+        );
+    }
+
+    #[test]
+    fn error_location() {
+        let text = "0x01\n  ( 0x02 0xzz )";
+        let (index, _) = parse::<u8>(text).unwrap_err();
+        assert_eq!(location(text, index), (2, 10));
+
+        let text = "0x01\n( 0x02";
+        let (index, _) = parse::<u8>(text).unwrap_err();
+        assert_eq!(location(text, index), (2, 1));
+    }
+}
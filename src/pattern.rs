@@ -1,12 +1,23 @@
-use crate::MaskedByte;
+use crate::code;
+use crate::ida;
+use crate::matcher::{Matcher, OpenRun, Outcome};
+use crate::parser;
+use crate::yara;
+use crate::{
+    CompiledPattern, Element, MaskedByte, PatternFindIterator, PatternFormatter, ReadError,
+};
+use snafu::ResultExt;
 use std::fmt;
+use std::io;
+use std::iter;
 use std::ops;
+use std::slice;
 use std::str;
 
-/// Pattern is a container of MaskedBytes.
-#[derive(Default)]
+/// Pattern is a container of Elements.
+#[derive(Default, Clone, PartialEq)]
 pub struct Pattern<T> {
-    values: Vec<MaskedByte<T>>,
+    values: Vec<Element<T>>,
 }
 
 impl<T> str::FromStr for Pattern<T>
@@ -23,14 +34,13 @@ where
 {
     type Err = crate::Error;
 
-    /// Input is a list of MaskedBytes separated with whitespace
+    /// Input is a list of Elements separated with whitespace. Elements can be
+    /// grouped with parentheses like "( 0x01 0x02 )" or "(?<name> 0x01 0x02 )" to
+    /// capture the bytes they match. Comments start with "#" or "//" and run to
+    /// the end of the line, a backslash at the end of a line joins it with the
+    /// next one.
     fn from_str(string: &str) -> Result<Self, Self::Err> {
-        let mut values = Vec::new();
-
-        for part in string.split_whitespace() {
-            let v: MaskedByte<T> = MaskedByte::from_str(part)?;
-            values.push(v);
-        }
+        let values = parser::parse(string).map_err(|(_, error)| error)?;
         Ok(Pattern { values })
     }
 }
@@ -48,6 +58,8 @@ impl<T> fmt::Display for Pattern<T>
 where
     T: num::PrimInt + ops::ShrAssign<u8>,
 {
+    /// Shows bytes as bit strings, PatternFormatter writes Patterns which can be
+    /// parsed again.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "[ ")?;
         for b in &self.values {
@@ -74,39 +86,405 @@ where
         Self::default()
     }
 
-    pub fn get(&self, index: usize) -> Option<&MaskedByte<T>> {
+    /// Reads a Pattern spanning multiple lines, see from_str. Errors report the
+    /// line and column they occurred at.
+    pub fn from_reader<R: io::Read>(mut reader: R) -> crate::Result<Self> {
+        let mut string = String::new();
+        reader.read_to_string(&mut string).context(ReadError)?;
+
+        let values = parser::parse(&string).map_err(|error| parser::locate(&string, error))?;
+        Ok(Pattern { values })
+    }
+
+    /// Creates a Pattern from a signature as written by IDA or x64dbg like
+    /// "48 8B ?? ?? 05 ? E8". Bytes are bare hex numbers, "?" and "??" match any
+    /// byte, single nibbles can be undefined like "4?".
+    pub fn from_ida_str(string: &str) -> crate::Result<Self> {
+        Ok(Pattern {
+            values: ida::parse(string)?,
+        })
+    }
+
+    /// Writes the Pattern as IDA or x64dbg signature, any byte is written as "??".
+    /// Only Patterns of fixed length with nibble wise defined bytes can be written.
+    pub fn to_ida_string(&self) -> crate::Result<String> {
+        ida::format(&self.masked_bytes()?)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Element<T>> {
         self.values.get(index)
     }
 
-    pub fn len(&self) -> usize {
+    /// Returns count of elements, for the count of bytes a match has see min_len
+    /// and max_len
+    pub fn element_count(&self) -> usize {
         self.values.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.len() == 0
+        self.values.is_empty()
     }
 
-    // data is taken, elements are replaced/added and returned
-    pub fn replace(&self, mut data: Vec<T>) -> crate::Result<Vec<T>> {
-        let mut index = 0;
+    /// Returns count of bytes the shortest possible match has
+    pub fn min_len(&self) -> usize {
+        self.values
+            .iter()
+            .map(|element| element.len_range().0)
+            .fold(0, usize::saturating_add)
+    }
+
+    /// Returns count of bytes the longest possible match has, None if unlimited
+    pub fn max_len(&self) -> Option<usize> {
+        self.values
+            .iter()
+            .map(|element| element.len_range().1)
+            .try_fold(0, |len: usize, max| max.map(|max| len.saturating_add(max)))
+    }
 
-        for masked_byte in &self.values {
-            match data.get_mut(index) {
-                Some(byte) => {
-                    *byte = masked_byte.set(*byte);
+    /// Returns the names of all groups by index, None for unnamed groups
+    pub fn group_names(&self) -> Vec<Option<String>> {
+        fn collect<T>(elements: &[Element<T>], names: &mut Vec<Option<String>>) {
+            for element in elements {
+                if let Element::Group {
+                    index,
+                    name,
+                    elements,
+                } = element
+                {
+                    if names.len() <= *index {
+                        names.resize(*index + 1, None);
+                    }
+                    names[*index] = name.clone();
+                    collect(elements, names);
+                } else if let Element::Assertion { elements, .. } = element {
+                    collect(elements, names);
+                }
+            }
+        }
+
+        let mut names = Vec::new();
+        collect(&self.values, &mut names);
+        names
+    }
+
+    // Returns one MaskedByte per byte a match has, along with the index of the top
+    // level element it belongs to. Gaps are undefined bytes, repeats and gaps need a
+    // fixed count and groups are flattened. Other elements can not be expressed as
+    // bytes.
+    pub(crate) fn masked_bytes(&self) -> crate::Result<Vec<(usize, MaskedByte<T>)>> {
+        fn collect<T>(
+            elements: &[Element<T>],
+            position: usize,
+            bytes: &mut Vec<(usize, MaskedByte<T>)>,
+        ) -> crate::Result<()>
+        where
+            T: From<u8>
+                + fmt::Binary
+                + num::PrimInt
+                + num::Unsigned
+                + Default
+                + ops::ShlAssign<u32>
+                + PartialEq
+                + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>
+                + ops::BitOrAssign
+                + ops::BitAndAssign,
+        {
+            for element in elements {
+                match element {
+                    Element::Byte(masked_byte) => bytes.push((position, *masked_byte)),
+                    Element::Gap { min, max } if min == max => {
+                        bytes.extend(iter::repeat_n((position, MaskedByte::default()), *min))
+                    }
+                    Element::Repeat {
+                        element: repeated,
+                        min,
+                        max: Some(max),
+                        ..
+                    } if min == max && matches!(**repeated, Element::Byte(_)) => {
+                        if let Element::Byte(masked_byte) = &**repeated {
+                            bytes.extend(iter::repeat_n((position, *masked_byte), *min));
+                        }
+                    }
+                    Element::Group { elements, .. } => collect(elements, position, bytes)?,
+                    Element::Typed(_) | Element::Literal(_) => {
+                        collect(element.sequence().unwrap_or_default(), position, bytes)?
+                    }
+                    _ => {
+                        return Err(crate::Error::FormatUnsupported {
+                            element: PatternFormatter::new().element(element),
+                            index: position,
+                        })
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        let mut bytes = Vec::new();
+        for (position, element) in self.values.iter().enumerate() {
+            collect(slice::from_ref(element), position, &mut bytes)?;
+        }
+        Ok(bytes)
+    }
+
+    pub(crate) fn elements(&self) -> &[Element<T>] {
+        &self.values
+    }
+
+    /// Returns true if every element matches exactly one byte
+    pub(crate) fn is_simple(&self) -> bool {
+        self.values.iter().all(Element::is_single)
+    }
+
+    // Count of bytes before a match lookbehind assertions may look at
+    pub(crate) fn behind(&self) -> usize {
+        self.values.iter().map(Element::behind).max().unwrap_or(0)
+    }
+
+    // Returns the single byte element every match starts with, None if there is
+    // no such element or it can not be told
+    pub(crate) fn first(&self) -> Option<&Element<T>> {
+        fn first<T>(elements: &[Element<T>]) -> Option<&Element<T>>
+        where
+            T: From<u8>
+                + fmt::Binary
+                + num::PrimInt
+                + num::Unsigned
+                + Default
+                + ops::ShlAssign<u32>
+                + PartialEq
+                + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>
+                + ops::BitOrAssign
+                + ops::BitAndAssign,
+        {
+            for element in elements {
+                match element {
+                    // Anchors and assertions take no bytes
+                    Element::Start | Element::End | Element::Assertion { .. } => {}
+                    Element::Repeat {
+                        element: repeated,
+                        min,
+                        ..
+                    } if *min > 0 && repeated.is_single() => return Some(repeated),
+                    Element::Group { elements, .. } => return first(elements),
+                    Element::Typed(_) | Element::Literal(_) => {
+                        return first(element.sequence().unwrap_or_default())
+                    }
+                    _ if element.is_single() => return Some(element),
+                    _ => return None,
+                }
+            }
+            None
+        }
+
+        first(&self.values)
+    }
+
+    // Returns true if matches may depend on bytes after them
+    pub(crate) fn ahead(&self) -> bool {
+        self.values.iter().any(Element::ahead)
+    }
+
+    // Matches the Pattern against data starting at origin, bytes before it are only
+    // seen by lookbehind assertions. If start is true data begins at the start of
+    // input, if end is false more data may follow. Up to budget single byte
+    // elements may fail to match.
+    pub(crate) fn match_at(
+        &self,
+        data: &[T],
+        origin: usize,
+        start: bool,
+        end: bool,
+        budget: usize,
+    ) -> Outcome {
+        Matcher::new(&self.values, data, origin, start, end, budget).run()
+    }
+
+    // Like match_at, a Partial outcome comes with the greedy repeat it waits on if
+    // more data may only continue its run
+    pub(crate) fn match_open(
+        &self,
+        data: &[T],
+        origin: usize,
+        start: bool,
+        end: bool,
+        budget: usize,
+    ) -> (Outcome, Option<OpenRun<'_, T>>) {
+        let matcher = Matcher::new(&self.values, data, origin, start, end, budget);
+        let outcome = matcher.run();
+        (outcome, matcher.open())
+    }
+
+    /// Returns a copy of the Pattern with precomputed tables for faster searching
+    pub fn compile(&self) -> CompiledPattern<T> {
+        CompiledPattern::new(self.clone())
+    }
+
+    /// Returns offset of the first match in haystack
+    pub fn find(&self, haystack: &[T]) -> Option<usize> {
+        self.find_iter(haystack).next().map(|(index, _)| index)
+    }
+
+    /// Returns an iterator over all non-overlapping matches in haystack. This is
+    /// faster than searching the slice's bytes one by one.
+    pub fn find_iter<'a, 'b>(&'a self, haystack: &'b [T]) -> PatternFindIterator<'a, 'b, T> {
+        PatternFindIterator::new(haystack, self)
+    }
+
+    /// Returns offset of the last match in haystack. This is the last match a
+    /// forward search finds, not necessarily the last offset the Pattern matches.
+    /// Patterns without maximal length or looking past their matches with the end
+    /// anchor or lookahead assertions search the whole haystack forward, see
+    /// rfind_iter.
+    pub fn rfind(&self, haystack: &[T]) -> Option<usize> {
+        self.find_iter(haystack).next_back().map(|(index, _)| index)
+    }
+
+    /// Returns an iterator over all non-overlapping matches in haystack, starting
+    /// with the last one. Patterns without maximal length or looking past their
+    /// matches find all matches with a forward search on the first call and keep
+    /// them, which takes memory for every match. Other Patterns keep the matches
+    /// of a run of overlapping ones, as where the run ends depends on where it
+    /// starts.
+    pub fn rfind_iter<'a, 'b>(
+        &'a self,
+        haystack: &'b [T],
+    ) -> iter::Rev<PatternFindIterator<'a, 'b, T>> {
+        self.find_iter(haystack).rev()
+    }
+
+    // Replaces byte at index in data or adds it if data is too short
+    fn replace_byte(
+        masked_byte: &MaskedByte<T>,
+        data: &mut Vec<T>,
+        index: usize,
+    ) -> crate::Result<()> {
+        match data.get_mut(index) {
+            Some(byte) => *byte = masked_byte.set(*byte),
+            None => match masked_byte.defined() {
+                Some(value) => data.push(value),
+                None => return Err(crate::Error::ReplaceNotDefined),
+            },
+        }
+        Ok(())
+    }
+
+    // Replaces elements in data starting at index, which is moved behind them.
+    // position is the index of the top level element they belong to.
+    fn replace_elements(
+        elements: &[Element<T>],
+        data: &mut Vec<T>,
+        index: &mut usize,
+        position: usize,
+    ) -> crate::Result<()> {
+        for element in elements {
+            match element {
+                Element::Byte(masked_byte) => {
+                    Self::replace_byte(masked_byte, data, *index)?;
+                    *index += 1;
+                }
+                Element::Gap { min, max } if min == max => {
+                    if *index + min > data.len() {
+                        return Err(crate::Error::ReplaceNotDefined);
+                    }
+                    *index += min;
+                }
+                Element::Repeat {
+                    element: repeated,
+                    min,
+                    max: Some(max),
+                    ..
+                } if min == max && matches!(**repeated, Element::Byte(_)) => {
+                    if let Element::Byte(masked_byte) = &**repeated {
+                        for _ in 0..*min {
+                            Self::replace_byte(masked_byte, data, *index)?;
+                            *index += 1;
+                        }
+                    }
+                }
+                Element::Group { elements, .. } => {
+                    Self::replace_elements(elements, data, index, position)?
+                }
+                Element::Start | Element::End | Element::Assertion { .. } => {}
+                Element::Typed(_) | Element::Literal(_) => {
+                    let sequence = element.sequence().unwrap_or_default();
+                    Self::replace_elements(sequence, data, index, position)?
+                }
+                Element::Alternatives(_)
+                | Element::Range { .. }
+                | Element::Not(_)
+                | Element::Gap { .. }
+                | Element::Repeat { .. } => {
+                    return Err(crate::Error::ReplaceUnsupported {
+                        element: PatternFormatter::new().element(element),
+                        index: position,
+                    })
                 }
-                None => match masked_byte.defined() {
-                    Some(value) => data.push(value),
-                    None => return Err(crate::Error::ReplaceNotDefined),
-                },
             }
+        }
+        Ok(())
+    }
 
-            index += 1;
+    // data is taken, elements are replaced/added and returned
+    // A gap of fixed size keeps the according bytes, a byte repeated a fixed count
+    // of times is replaced like that many bytes. Groups, typed values and literals
+    // are replaced like their elements. Anchors and assertions replace nothing.
+    pub fn replace(&self, mut data: Vec<T>) -> crate::Result<Vec<T>> {
+        let mut index = 0;
+        for (position, element) in self.values.iter().enumerate() {
+            Self::replace_elements(slice::from_ref(element), &mut data, &mut index, position)?;
         }
         Ok(data)
     }
 }
 
+impl Pattern<u8> {
+    // Returns the masked bytes without the element indices
+    fn bytes(&self) -> crate::Result<Vec<MaskedByte<u8>>> {
+        Ok(self
+            .masked_bytes()?
+            .into_iter()
+            .map(|(_, byte)| byte)
+            .collect())
+    }
+
+    /// Creates a Pattern from a byte string like "\x48\x8B\x00\x05" and a mask
+    /// like "xx?x", where "x" marks defined and "?" undefined bytes. Both may be
+    /// enclosed in quotes.
+    pub fn from_code(bytes: &str, mask: &str) -> crate::Result<Self> {
+        Ok(Pattern {
+            values: code::parse(bytes, mask)?,
+        })
+    }
+
+    /// Creates a Pattern from a YARA hex string like "{ 4D 5A ?? [2-4] (6A | 6B) }".
+    /// Jumps become gaps or lazy repeats, alternatives need to be single bytes.
+    /// Errors report the line and column they occurred at.
+    pub fn from_yara_str(string: &str) -> crate::Result<Self> {
+        let values = yara::parse(string).map_err(|error| parser::locate(string, error))?;
+        Ok(Pattern { values })
+    }
+
+    /// Writes the Pattern as byte string and mask, see from_code. Only Patterns of
+    /// fixed length with fully defined or undefined bytes can be written.
+    pub fn to_code(&self) -> crate::Result<(String, String)> {
+        code::format(&self.masked_bytes()?)
+    }
+
+    /// Writes the Pattern as C/C++ arrays name_bytes and name_mask. Bytes match if
+    /// (byte & mask) == value. name needs to be an identifier of ASCII letters,
+    /// digits and underscores not starting with a digit.
+    pub fn to_c_arrays(&self, name: &str) -> crate::Result<String> {
+        code::c_arrays(&self.bytes()?, name)
+    }
+
+    /// Writes the Pattern as Rust constants NAME_BYTES and NAME_MASK, see
+    /// to_c_arrays.
+    pub fn to_rust_arrays(&self, name: &str) -> crate::Result<String> {
+        code::rust_arrays(&self.bytes()?, name)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -117,7 +495,7 @@ mod tests {
         let p = Pattern::<u8>::from_str("0b?01? 0x?f");
         assert!(p.is_ok());
         let p = p.unwrap();
-        assert_eq!(p.len(), 2);
+        assert_eq!(p.element_count(), 2);
     }
 
     #[test]
@@ -145,4 +523,299 @@ mod tests {
         let result = p.replace(source);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn string_conversion_gap() {
+        let p = Pattern::<u8>::from_str("0x01 [2-8] 0x02").unwrap();
+        assert_eq!(p.element_count(), 3);
+        assert_eq!(p.min_len(), 4);
+        assert_eq!(p.max_len(), Some(10));
+        assert_eq!(p.to_string(), "[ 00000001 [2-8] 00000010 ]");
+        assert!(Pattern::<u8>::from_str("0x01 [8-2]").is_err());
+        assert!(Pattern::<u8>::from_str("0x41 [0-18446744073709551615] [1]").is_err());
+
+        // Lengths saturate instead of overflowing
+        let gap = Element::Gap {
+            min: usize::MAX,
+            max: usize::MAX,
+        };
+        let p = Pattern::<u8> {
+            values: vec![
+                Element::from_str("0x41").unwrap(),
+                Element::Group {
+                    index: 1,
+                    name: None,
+                    elements: vec![gap.clone(), Element::Gap { min: 2, max: 2 }],
+                },
+                gap,
+            ],
+        };
+        assert_eq!(p.min_len(), usize::MAX);
+        assert_eq!(p.max_len(), Some(usize::MAX));
+    }
+
+    #[test]
+    fn replace_gap() {
+        let p = Pattern::<u8>::from_str("0x?a [2] 0x3c").unwrap();
+        let result = p.replace(vec![0x12, 0x1b, 0x2c, 0x4d]);
+        assert_eq!(result.unwrap(), [0x1a, 0x1b, 0x2c, 0x3c]);
+
+        let p = Pattern::<u8>::from_str("0x?a [1-2] 0x3c").unwrap();
+        assert!(p.replace(vec![0x12, 0x1b, 0x2c, 0x4d]).is_err());
+    }
+
+    #[test]
+    fn string_conversion_alternatives() {
+        let p = Pattern::<u8>::from_str("0x01 ( 0x40 | 0x80 ) (0x1?|0x2?) 0x02").unwrap();
+        assert_eq!(p.element_count(), 4);
+        assert_eq!(
+            p.to_string(),
+            "[ 00000001 (01000000|10000000) (0001????|0010????) 00000010 ]"
+        );
+        assert!(Pattern::<u8>::from_str("0x01 (0x40|0x80").is_err());
+        assert!(Pattern::<u8>::from_str("0x01 ((0x40)|0x80)").is_err());
+    }
+
+    #[test]
+    fn replace_alternatives() {
+        let p = Pattern::<u8>::from_str("(0x40|0x80)").unwrap();
+        assert!(p.replace(vec![0x40]).is_err());
+    }
+
+    #[test]
+    fn replace_range() {
+        let p = Pattern::<u8>::from_str("0x41 0x20-0x7e").unwrap();
+        let error = p.replace(vec![0x41, 0x42]).unwrap_err();
+        assert!(matches!(error, crate::Error::ReplaceUnsupported { .. }));
+    }
+
+    #[test]
+    fn string_conversion_repeat() {
+        let p = Pattern::<u8>::from_str("0x55 0x??{2,4} 0xc3").unwrap();
+        assert_eq!(p.element_count(), 3);
+        assert_eq!(p.min_len(), 4);
+        assert_eq!(p.max_len(), Some(6));
+
+        let p = Pattern::<u8>::from_str("0x55 0x00{4,} 0xc3").unwrap();
+        assert_eq!(p.min_len(), 6);
+        assert_eq!(p.max_len(), None);
+        assert!(Pattern::<u8>::from_str("0x41 0x??{18446744073709551615} 0x41{1}").is_err());
+    }
+
+    #[test]
+    fn replace_repeat() {
+        let p = Pattern::<u8>::from_str("0x?0 0x90{3}").unwrap();
+        let result = p.replace(vec![0x12, 0x34]);
+        assert_eq!(result.unwrap(), [0x10, 0x90, 0x90, 0x90]);
+
+        let p = Pattern::<u8>::from_str("0x90{2,3}").unwrap();
+        assert!(p.replace(vec![0x12]).is_err());
+    }
+
+    #[test]
+    fn string_conversion_group() {
+        let p = Pattern::<u8>::from_str("0x01 (?<addr> 0x?? [2] ) (0x40|0x80)").unwrap();
+        assert_eq!(p.element_count(), 3);
+        assert_eq!(p.min_len(), 5);
+        assert_eq!(p.group_names(), [Some("addr".to_string())]);
+        assert_eq!(
+            p.to_string(),
+            "[ 00000001 (?<addr> ???????? [2] ) (01000000|10000000) ]"
+        );
+    }
+
+    #[test]
+    fn replace_group() {
+        let p = Pattern::<u8>::from_str("0x?a ( 0x2? [1] 0x3c )").unwrap();
+        let result = p.replace(vec![0x12, 0x1b, 0x4d]);
+        assert_eq!(result.unwrap(), [0x1a, 0x2b, 0x4d, 0x3c]);
+    }
+
+    #[test]
+    fn string_conversion_typed() {
+        let p = Pattern::<u8>::from_str("0x68 u32le:0x12??56?? i16be:-5").unwrap();
+        assert_eq!(p.element_count(), 3);
+        assert_eq!(p.min_len(), 7);
+        assert_eq!(p.to_string(), "[ 01101000 u32le:0x12??56?? i16be:-5 ]");
+        assert_eq!(
+            p.find(&[0x00, 0x68, 0xaa, 0x56, 0xbb, 0x12, 0xff, 0xfb]),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn replace_typed() {
+        let p = Pattern::<u8>::from_str("0xb8 u32le:0x1234??78").unwrap();
+        let result = p.replace(vec![0x00, 0x00, 0xab, 0x00, 0x00]);
+        assert_eq!(result.unwrap(), [0xb8, 0x78, 0xab, 0x34, 0x12]);
+    }
+
+    #[test]
+    fn string_conversion_literal() {
+        let p = Pattern::<u8>::from_str(r#"0x?? "Error %d\n" 0x00 u"Hi you""#).unwrap();
+        assert_eq!(p.element_count(), 4);
+        assert_eq!(p.min_len(), 23);
+        assert_eq!(
+            p.to_string(),
+            r#"[ ???????? "Error %d\n" 00000000 u"Hi you" ]"#
+        );
+        assert_eq!(p.find(b"__Error %d\n\0H\0i\0 \0y\0o\0u\0"), Some(1));
+    }
+
+    #[test]
+    fn replace_literal() {
+        let p = Pattern::<u8>::from_str(r#""ok" 0x00"#).unwrap();
+        let result = p.replace(b"fail".to_vec());
+        assert_eq!(result.unwrap(), b"ok\0l");
+    }
+
+    #[test]
+    fn from_reader() {
+        let text = "\
+# Call with relative address
+0xe8 (?<addr> [4] )   // call rel32
+
+// Return
+0xc3
+";
+        let p = Pattern::<u8>::from_reader(text.as_bytes()).unwrap();
+        assert_eq!(p.element_count(), 3);
+
+        let text = "0xe8 [4]\n  0xc3 0xzz\n";
+        match Pattern::<u8>::from_reader(text.as_bytes()) {
+            Err(crate::Error::Location { line, column, .. }) => {
+                assert_eq!((line, column), (2, 8));
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn string_conversion_anchors() {
+        let p = Pattern::<u8>::from_str("^0x4d 0x5a [0-4] $").unwrap();
+        assert_eq!(p.element_count(), 5);
+        assert_eq!(p.min_len(), 2);
+        assert_eq!(p.to_string(), "[ ^ 01001101 01011010 [0-4] $ ]");
+    }
+
+    #[test]
+    fn replace_negation() {
+        let p = Pattern::<u8>::from_str("0x41 !0x00").unwrap();
+        let error = p.replace(vec![0x41, 0x42]).unwrap_err();
+        assert!(matches!(error, crate::Error::ReplaceUnsupported { .. }));
+        assert_eq!(
+            error.to_string(),
+            "Element !0x00 at index 1 is not supported in a replace pattern"
+        );
+
+        let p = Pattern::<u8>::from_str("0x41 ( 0x42 (0x43|0x44) )").unwrap();
+        let error = p.replace(vec![0x41, 0x42, 0x43]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Element (0x43|0x44) at index 1 is not supported in a replace pattern"
+        );
+    }
+
+    #[test]
+    fn first_element() {
+        let first = |pattern: &str| {
+            Pattern::<u8>::from_str(pattern)
+                .unwrap()
+                .first()
+                .map(ToString::to_string)
+        };
+        assert_eq!(first("0x41 0x42"), Some("01000001".to_string()));
+        assert_eq!(
+            first("^ (?<= 0x40 ) ( 0x4? )"),
+            Some("0100????".to_string())
+        );
+        assert_eq!(first("0x41+ 0x42"), Some("01000001".to_string()));
+        assert_eq!(first("\"A\""), Some("01000001".to_string()));
+        assert_eq!(first("0x41* 0x42"), None);
+        assert_eq!(first("[1] 0x42"), None);
+        assert_eq!(first(""), None);
+    }
+
+    #[test]
+    fn ida_conversion() {
+        let p = Pattern::<u8>::from_ida_str("48 8B ?? ? 05 4? E8").unwrap();
+        assert_eq!(p.element_count(), 7);
+        assert_eq!(
+            p.find(&[0x00, 0x48, 0x8b, 0x01, 0x02, 0x05, 0x41, 0xe8]),
+            Some(1)
+        );
+        assert_eq!(p.to_ida_string().unwrap(), "48 8B ?? ?? 05 4? E8");
+
+        let p = Pattern::<u8>::from_str("( 0x48 0x8? ) [2] 0xe8{2} u16le:0x1234").unwrap();
+        assert_eq!(p.to_ida_string().unwrap(), "48 8? ?? ?? E8 E8 34 12");
+
+        assert!(Pattern::<u8>::from_ida_str("48 0x8B").is_err());
+        assert!(Pattern::<u8>::from_str("0x48 [1-2]")
+            .unwrap()
+            .to_ida_string()
+            .is_err());
+        assert!(Pattern::<u8>::from_str("0b0100100?")
+            .unwrap()
+            .to_ida_string()
+            .is_err());
+
+        let error = Pattern::<u8>::from_str("0x48 ( 0x8b 0x??+ )")
+            .unwrap()
+            .to_ida_string()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Element 0x??+ at index 1 can not be written in this format"
+        );
+    }
+
+    #[test]
+    fn code_conversion() {
+        let p = Pattern::from_code(r#""\x48\x8B\x00\x00\x05""#, "xx??x").unwrap();
+        assert_eq!(p.find(&[0x48, 0x8b, 0x01, 0x02, 0x05]), Some(0));
+        assert_eq!(
+            p.to_code().unwrap(),
+            (r"\x48\x8B\x00\x00\x05".to_string(), "xx??x".to_string())
+        );
+
+        let p = Pattern::<u8>::from_str("0x48 [1] 0x4?").unwrap();
+        assert!(p.to_code().is_err());
+        assert_eq!(
+            p.to_rust_arrays("sig").unwrap(),
+            "const SIG_BYTES: [u8; 3] = [0x48, 0x00, 0x40];\n\
+             const SIG_MASK: [u8; 3] = [0xff, 0x00, 0xf0];\n"
+        );
+        assert!(p
+            .to_c_arrays("sig")
+            .unwrap()
+            .starts_with("const unsigned char sig_bytes[3]"));
+    }
+
+    #[test]
+    fn yara_conversion() {
+        let p = Pattern::from_yara_str("{ 4D 5A ?? [2-4] (6A | 6B) }").unwrap();
+        assert_eq!(p.find(&[0x00, 0x4d, 0x5a, 0x00, 0x01, 0x02, 0x6b]), Some(1));
+        assert_eq!(p.find(&[0x4d, 0x5a, 0x00, 0x01, 0x6b]), None);
+
+        match Pattern::from_yara_str("{ 4D 5A\n  (6A 00 | 6B) }") {
+            Err(crate::Error::Location {
+                line: 2,
+                column: 3,
+                source,
+            }) => assert!(matches!(*source, crate::Error::YaraUnsupported { .. })),
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn string_conversion_assertion() {
+        let p = Pattern::<u8>::from_str("(?<=0x01) 0x02 (?! 0x03 0x04 )").unwrap();
+        assert_eq!(
+            p.to_string(),
+            "[ (?<= 00000001 ) 00000010 (?! 00000011 00000100 ) ]"
+        );
+        assert_eq!((p.min_len(), p.max_len()), (1, Some(1)));
+        assert_eq!(p.behind(), 1);
+        assert_eq!(p.replace(vec![0x00]).unwrap(), vec![0x02]);
+    }
 }
@@ -0,0 +1,476 @@
+use crate::matcher::Outcome;
+use crate::{Pattern, MAX_MATCH_LEN};
+use std::collections::VecDeque;
+use std::fmt;
+use std::iter::Iterator;
+use std::ops;
+
+/// Iterator over all non-overlapping matches of a Pattern in a slice. Returns the
+/// same matches as a search over the slice's bytes, so a match spans at most
+/// MAX_MATCH_LEN bytes, but only tests positions where the anchor element of the
+/// Pattern matches.
+/// Iterating from the back returns the same matches in reverse order.
+pub struct PatternFindIterator<'a, 'b, T> {
+    pattern: &'a Pattern<T>,
+    haystack: &'b [T],
+
+    // Offset where the next match can start at the earliest
+    position: usize,
+
+    // Offset where the last match has to end at the latest
+    end: usize,
+
+    // Offset and value of the rarest fully defined element of pattern
+    anchor: Option<(usize, T)>,
+
+    // Offsets and lengths of the last remaining matches of a forward search, found
+    // ahead of time when iterating from the back needs them
+    chain: VecDeque<(usize, usize)>,
+
+    // Chain holds all remaining matches
+    complete: bool,
+}
+
+// Rough estimate how rare a byte value is in binary data, higher is rarer.
+// Zero and all ones are very common, followed by small numbers and text.
+fn rarity(byte: u8) -> u8 {
+    match byte {
+        0x00 => 0,
+        0xff => 1,
+        0x01..=0x0f => 2,
+        b' ' | b'a'..=b'z' => 3,
+        b'0'..=b'9' | b'A'..=b'Z' => 4,
+        0x10..=0x7f => 5,
+        0x80..=0xfe => 6,
+    }
+}
+
+impl<'a, 'b, T> PatternFindIterator<'a, 'b, T>
+where
+    T: From<u8>
+        + fmt::Binary
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + ops::ShlAssign<u32>
+        + PartialEq
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>
+        + ops::BitOrAssign
+        + ops::BitAndAssign,
+{
+    pub fn new(haystack: &'b [T], pattern: &'a Pattern<T>) -> Self {
+        // Values not fitting into a byte are considered rare
+        let rank = |value: T| value.to_u8().map_or(u8::MAX, rarity);
+        let mut anchor: Option<(usize, T)> = None;
+
+        // The anchor needs a fixed offset from the start of a match
+        for index in 0..pattern.element_count() {
+            let element = pattern.get(index).unwrap();
+            if !element.is_single() {
+                break;
+            }
+
+            if let Some(value) = element.defined() {
+                let rarer = match anchor {
+                    None => true,
+                    Some((_, other)) => rank(value) > rank(other),
+                };
+                if rarer {
+                    anchor = Some((index, value));
+                }
+            }
+        }
+
+        Self {
+            pattern,
+            haystack,
+            position: 0,
+            end: haystack.len(),
+            anchor,
+            chain: VecDeque::new(),
+            complete: false,
+        }
+    }
+
+    // Returns offset of the last possible start of a match ending before end
+    fn last_start(&self, end: usize) -> Option<usize> {
+        // Empty matches are not reported
+        let min = self.pattern.min_len().max(1);
+        if self.pattern.is_empty() || min > end {
+            None
+        } else {
+            Some(end - min)
+        }
+    }
+
+    // Returns offset of the first candidate start between from and to
+    fn candidate(&self, from: usize, to: usize) -> Option<usize> {
+        if from > to {
+            return None;
+        }
+
+        match self.anchor {
+            None => Some(from),
+            Some((offset, value)) => self.haystack[from + offset..=to + offset]
+                .iter()
+                .position(|byte| *byte == value)
+                .map(|found| from + found),
+        }
+    }
+
+    // Returns offset of the last candidate start between from and to
+    fn candidate_back(&self, from: usize, to: usize) -> Option<usize> {
+        if from > to {
+            return None;
+        }
+
+        match self.anchor {
+            None => Some(to),
+            Some((offset, value)) => self.haystack[from + offset..=to + offset]
+                .iter()
+                .rposition(|byte| *byte == value)
+                .map(|found| from + found),
+        }
+    }
+
+    // Returns length of a match at start ending before end, lookahead assertions
+    // see no bytes after end. Like in a search a match spans at most MAX_MATCH_LEN
+    // bytes, and patterns looking past that many bytes do not match.
+    fn match_len(&self, start: usize, end: usize) -> Option<usize> {
+        let end = if end - start >= MAX_MATCH_LEN {
+            if self.pattern.ahead() {
+                return None;
+            }
+            start + MAX_MATCH_LEN
+        } else {
+            end
+        };
+        match self
+            .pattern
+            .match_at(&self.haystack[..end], start, true, true, 0)
+        {
+            Outcome::Match { len, .. } if len > 0 && start + len <= end => Some(len),
+            _ => None,
+        }
+    }
+
+    // Returns offset and length of the first match between from and end
+    fn first_match(&self, mut from: usize, end: usize) -> Option<(usize, usize)> {
+        let to = self.last_start(end)?;
+        while let Some(start) = self.candidate(from, to) {
+            if let Some(len) = self.match_len(start, end) {
+                return Some((start, len));
+            }
+            from = start + 1;
+        }
+        None
+    }
+
+    // Returns offset of the last match between from and end, which is not
+    // necessarily one a forward search finds
+    fn last_match(&self, from: usize, end: usize) -> Option<usize> {
+        let mut to = self.last_start(end)?;
+        while let Some(start) = self.candidate_back(from, to) {
+            if self.match_len(start, end).is_some() {
+                return Some(start);
+            }
+            if start == 0 {
+                break;
+            }
+            to = start - 1;
+        }
+        None
+    }
+
+    // Returns offsets and lengths of the matches of a forward search from from
+    fn forward(&self, mut from: usize) -> VecDeque<(usize, usize)> {
+        let mut chain = VecDeque::new();
+        while let Some((start, len)) = self.first_match(from, self.end) {
+            chain.push_back((start, len));
+            from = start + len;
+        }
+        chain
+    }
+
+    // Fills chain with the last matches of a forward search. The last match might
+    // be overlapped by an earlier match a forward search takes instead. A match
+    // with no other match starting less than the maximal match length before it is
+    // found by a forward search for sure, so walk back to such a match and keep
+    // all matches from there. Later calls continue in front of them, so every
+    // offset is walked back over once.
+    fn sync(&mut self) {
+        let mut sync = match self.last_match(self.position, self.end) {
+            Some(last) => last,
+            None => return,
+        };
+        let max = self
+            .pattern
+            .max_len()
+            .unwrap_or_default()
+            .min(MAX_MATCH_LEN);
+        while let Some((earlier, _)) = self
+            .first_match((sync + 1).saturating_sub(max).max(self.position), self.end)
+            .filter(|(earlier, _)| *earlier < sync)
+        {
+            sync = earlier;
+        }
+        self.chain = self.forward(sync);
+    }
+}
+
+impl<'a, 'b, T> Iterator for PatternFindIterator<'a, 'b, T>
+where
+    T: From<u8>
+        + fmt::Binary
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + ops::ShlAssign<u32>
+        + PartialEq
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>
+        + ops::BitOrAssign
+        + ops::BitAndAssign,
+{
+    /// Offset and data of a match
+    type Item = (usize, &'b [T]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let found = if self.complete {
+            self.chain.pop_front()
+        } else {
+            self.first_match(self.position, self.end)
+        };
+        match found {
+            Some((start, len)) => {
+                self.position = start + len;
+                // Forward matches reaching the chain are the ones it starts with
+                while self
+                    .chain
+                    .front()
+                    .is_some_and(|(next, _)| *next < self.position)
+                {
+                    self.chain.pop_front();
+                }
+                Some((start, &self.haystack[start..start + len]))
+            }
+            None => {
+                self.position = self.end;
+                None
+            }
+        }
+    }
+}
+
+impl<'a, 'b, T> DoubleEndedIterator for PatternFindIterator<'a, 'b, T>
+where
+    T: From<u8>
+        + fmt::Binary
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + ops::ShlAssign<u32>
+        + PartialEq
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>
+        + ops::BitOrAssign
+        + ops::BitAndAssign,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        // Without a maximal match length there is no point to resume a forward
+        // search from, and matches looking past their end may depend on bytes of
+        // the match after them. The remaining matches are then found in a single
+        // forward pass.
+        if !self.complete && (self.pattern.max_len().is_none() || self.pattern.ahead()) {
+            self.chain = self.forward(self.position);
+            self.complete = true;
+        }
+        if self.chain.is_empty() && !self.complete {
+            self.sync();
+        }
+
+        match self.chain.pop_back() {
+            Some((start, len)) => {
+                self.end = start;
+                Some((start, &self.haystack[start..start + len]))
+            }
+            None => {
+                self.end = self.position;
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::pseudo_random;
+    use crate::{PatternSearchExt, PatternSearchType};
+    use std::str::FromStr;
+
+    #[test]
+    fn find_all() {
+        let d = [0x00, 0x41, 0x00, 0x41, 0x00, 0x41, 0x42, 0x00, 0x41];
+        let s = Pattern::<u8>::from_str("0x00 0x41 0x?? 0x??").unwrap();
+        let found: Vec<(usize, &[u8])> = s.find_iter(&d).collect();
+        assert_eq!(
+            found,
+            [
+                (0, &[0x00, 0x41, 0x00, 0x41][..]),
+                (4, &[0x00, 0x41, 0x42, 0x00][..])
+            ]
+        );
+    }
+
+    #[test]
+    fn anchor_is_rarest() {
+        let s = Pattern::<u8>::from_str("0x00 0x4? 0x8b 0x41").unwrap();
+        let d = [0u8; 4];
+        let iter = s.find_iter(&d);
+        assert_eq!(iter.anchor, Some((2, 0x8b)));
+    }
+
+    #[test]
+    fn same_as_search() {
+        let d = [
+            0x41, 0x41, 0x41, 0x42, 0x41, 0x42, 0x41, 0x41, 0x42, 0x42, 0x41, 0x41, 0x42,
+        ];
+        for pattern in &[
+            "0x41 0x41 0x42",
+            "0x4? 0x42",
+            "0x?? 0x?? 0x??",
+            "0x42 0x4?",
+            "0x42 [0-3] 0x42",
+            "(0x41|0x42) 0x42",
+            "0x41 0x41-0x42 0x41",
+            "0x41 !0x41",
+            "^ 0x41",
+            "0x42 $",
+            "0x4? [0-2] $",
+            "0x41 0x42*",
+            "0x41{2,} 0x4?",
+            "0x41+? 0x42",
+            "0x41 (?= 0x42 0x42 )",
+            "(?<= 0x42 [0-1] ) 0x41",
+            "(?<! 0x41 ) 0x4?",
+        ] {
+            let s = Pattern::<u8>::from_str(pattern).unwrap();
+            let found: Vec<usize> = s.find_iter(&d).map(|(index, _)| index).collect();
+            let searched: Vec<usize> = d
+                .iter()
+                .copied()
+                .search_pattern(&s)
+                .filter_map(|result| match result {
+                    PatternSearchType::Match { index, .. } => Some(index),
+                    PatternSearchType::NonMatch(_) => None,
+                })
+                .collect();
+            assert_eq!(found, searched, "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn limited_as_search() {
+        // Matches span at most MAX_MATCH_LEN bytes, like in a search
+        let d = vec![0x41; MAX_MATCH_LEN + 10];
+        for pattern in &["0x41 0x??*", "0x41 0x41* $"] {
+            let s = Pattern::<u8>::from_str(pattern).unwrap();
+            let found: Vec<(usize, usize)> = s
+                .find_iter(&d)
+                .map(|(index, data)| (index, data.len()))
+                .collect();
+            let searched: Vec<(usize, usize)> = d
+                .iter()
+                .copied()
+                .search_pattern(&s)
+                .filter_map(|result| match result {
+                    PatternSearchType::Match { index, data, .. } => Some((index, data.len())),
+                    PatternSearchType::NonMatch(_) => None,
+                })
+                .collect();
+            assert_eq!(found, searched, "{}", pattern);
+
+            let mut backward: Vec<(usize, usize)> = s
+                .rfind_iter(&d)
+                .map(|(index, data)| (index, data.len()))
+                .collect();
+            backward.reverse();
+            assert_eq!(found, backward, "{}", pattern);
+        }
+
+        let s = Pattern::<u8>::from_str("0x41 0x??*").unwrap();
+        let found: Vec<usize> = s.find_iter(&d).map(|(_, data)| data.len()).collect();
+        assert_eq!(found, [MAX_MATCH_LEN, 10]);
+    }
+
+    #[test]
+    fn find_back() {
+        let d = [0x41, 0x41, 0x41, 0x42, 0x41, 0x41, 0x41];
+        let s = Pattern::<u8>::from_str("0x41 0x41").unwrap();
+        let found: Vec<usize> = s.rfind_iter(&d).map(|(index, _)| index).collect();
+        assert_eq!(found, [4, 0]);
+        assert_eq!(s.rfind(&d), Some(4));
+        assert_eq!(s.rfind(&d[..5]), Some(0));
+
+        // Every match of an unlimited Pattern is found once
+        let d = vec![0x41; 100_000];
+        let s = Pattern::<u8>::from_str("0x41 0x42*").unwrap();
+        assert_eq!(s.rfind_iter(&d).count(), d.len());
+        assert_eq!(s.rfind(&d), Some(d.len() - 1));
+    }
+
+    #[test]
+    fn find_back_scales() {
+        // Every match of a long run of overlapping ones is found by walking back
+        // over the run once, not once per match
+        let d = vec![0xff; 200_000];
+        let s = Pattern::<u8>::from_str("0xff 0xff").unwrap();
+        let found: Vec<usize> = s.rfind_iter(&d).map(|(index, _)| index).collect();
+        assert_eq!(found.len(), d.len() / 2);
+        assert!(found.iter().rev().copied().eq((0..d.len()).step_by(2)));
+        assert_eq!(s.rfind(&d[..d.len() - 1]), Some(d.len() - 4));
+
+        let s = Pattern::<u8>::from_str("0xff [0-1] 0xff").unwrap();
+        assert_eq!(s.rfind_iter(&d).count(), d.len() / 2);
+    }
+
+    #[test]
+    fn find_both_ends() {
+        let d = pseudo_random(7, 500, 0x01);
+
+        for pattern in &[
+            "0x41 0x41 0x41",
+            "0x4? 0x41 0x40 0x41",
+            "0x40",
+            "0x41 [1-4] 0x41",
+            "[0-2] 0x40 [2] 0x40",
+            "0x40 0x41+",
+            "0x40{2,3}? 0x41",
+            "0x41 (?= 0x40 0x40 )",
+            "0x40 (?! 0x41 )",
+            "0x41 0x40* (?= 0x41 )",
+            "0x40 (?= 0x?? 0x41 )",
+            "0x41 $",
+        ] {
+            let s = Pattern::<u8>::from_str(pattern).unwrap();
+            let forward: Vec<usize> = s.find_iter(&d).map(|(index, _)| index).collect();
+            let mut backward: Vec<usize> = s.rfind_iter(&d).map(|(index, _)| index).collect();
+            backward.reverse();
+            assert_eq!(forward, backward, "{}", pattern);
+
+            // Alternating between both ends meets in the middle
+            let mut iter = s.find_iter(&d);
+            let mut front = Vec::new();
+            let mut back = Vec::new();
+            while let Some((index, _)) = iter.next() {
+                front.push(index);
+                match iter.next_back() {
+                    Some((index, _)) => back.push(index),
+                    None => break,
+                }
+            }
+            back.reverse();
+            front.append(&mut back);
+            assert_eq!(forward, front, "{}", pattern);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::element::quantifier;
+use crate::{Element, MaskedByte, Pattern};
+use std::fmt;
+use std::ops;
+
+/// Radix MaskedBytes are written in by a PatternFormatter
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Radix {
+    /// Hexadecimal if every nibble is fully defined or fully undefined, binary
+    /// otherwise
+    Hex,
+
+    /// Always binary
+    Binary,
+}
+
+/// PatternFormatter writes Patterns in the syntax Pattern::from_str reads, so
+/// parsing the output gives the same Pattern again. Elements are separated with
+/// a space by default, other separators need to be whitespace.
+#[derive(Debug, Clone)]
+pub struct PatternFormatter {
+    radix: Radix,
+    separator: String,
+
+    // Count of top level elements after which group_separator is written instead
+    // of separator
+    group: Option<(usize, String)>,
+}
+
+impl Default for PatternFormatter {
+    fn default() -> Self {
+        Self {
+            radix: Radix::Hex,
+            separator: " ".to_string(),
+            group: None,
+        }
+    }
+}
+
+// Returns separator if it is whitespace, the output would not parse otherwise
+fn whitespace(separator: &str) -> crate::Result<String> {
+    if !separator.is_empty() && separator.chars().all(char::is_whitespace) {
+        Ok(separator.to_string())
+    } else {
+        Err(crate::Error::InvalidSeparator {
+            string: separator.to_string(),
+        })
+    }
+}
+
+impl PatternFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn radix(mut self, radix: Radix) -> Self {
+        self.radix = radix;
+        self
+    }
+
+    /// Sets the whitespace written between elements, fails if separator is empty
+    /// or contains other chars
+    pub fn separator(mut self, separator: &str) -> crate::Result<Self> {
+        self.separator = whitespace(separator)?;
+        Ok(self)
+    }
+
+    /// Writes group_separator like "\n" after every count top level elements. It
+    /// needs to be whitespace like separator.
+    pub fn group(mut self, count: usize, group_separator: &str) -> crate::Result<Self> {
+        self.group = Some((count.max(1), whitespace(group_separator)?));
+        Ok(self)
+    }
+
+    pub fn format<T>(&self, pattern: &Pattern<T>) -> String
+    where
+        T: From<u8>
+            + fmt::Binary
+            + num::PrimInt
+            + num::Unsigned
+            + Default
+            + ops::ShlAssign<u32>
+            + PartialEq
+            + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>
+            + ops::BitOrAssign
+            + ops::BitAndAssign,
+    {
+        let mut string = String::new();
+        for (index, element) in pattern.elements().iter().enumerate() {
+            if index > 0 {
+                match &self.group {
+                    Some((count, group_separator)) if index % count == 0 => {
+                        string.push_str(group_separator)
+                    }
+                    _ => string.push_str(&self.separator),
+                }
+            }
+            string.push_str(&self.element(element));
+        }
+        string
+    }
+
+    /// Writes a single MaskedByte
+    pub(crate) fn masked_byte<T>(&self, masked_byte: &MaskedByte<T>) -> String
+    where
+        T: From<u8>
+            + ops::ShlAssign<u32>
+            + ops::BitOrAssign
+            + ops::BitAndAssign
+            + PartialEq
+            + num::PrimInt
+            + num::Unsigned
+            + Default
+            + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+    {
+        let bits = T::zero().count_zeros();
+        let (value, mask) = (masked_byte.value(), masked_byte.mask());
+        let part = |number: T, shift: u32, digit_bits: u32| {
+            let digit_mask = <T as From<u8>>::from((1 << digit_bits) - 1);
+            ((number >> shift as usize) & digit_mask).to_u32().unwrap()
+        };
+        let hex = self.radix == Radix::Hex
+            && (0..bits / 4).all(|digit| matches!(part(mask, digit * 4, 4), 0 | 0xf));
+
+        let (prefix, digit_bits) = if hex { ("0x", 4) } else { ("0b", 1) };
+        let mut string = prefix.to_string();
+        for digit in (0..bits / digit_bits).rev() {
+            let shift = digit * digit_bits;
+            if part(mask, shift, digit_bits) == 0 {
+                string.push('?');
+            } else {
+                string.push(std::char::from_digit(part(value, shift, digit_bits), 16).unwrap());
+            }
+        }
+        string
+    }
+
+    fn elements<T>(&self, elements: &[Element<T>]) -> String
+    where
+        T: From<u8>
+            + fmt::Binary
+            + num::PrimInt
+            + num::Unsigned
+            + Default
+            + ops::ShlAssign<u32>
+            + PartialEq
+            + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>
+            + ops::BitOrAssign
+            + ops::BitAndAssign,
+    {
+        elements
+            .iter()
+            .map(|element| self.element(element) + &self.separator)
+            .collect()
+    }
+
+    /// Writes a single element
+    pub(crate) fn element<T>(&self, element: &Element<T>) -> String
+    where
+        T: From<u8>
+            + fmt::Binary
+            + num::PrimInt
+            + num::Unsigned
+            + Default
+            + ops::ShlAssign<u32>
+            + PartialEq
+            + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>
+            + ops::BitOrAssign
+            + ops::BitAndAssign,
+    {
+        match element {
+            Element::Byte(masked_byte) => self.masked_byte(masked_byte),
+            Element::Alternatives(alternatives) => {
+                let values: Vec<String> = alternatives
+                    .values()
+                    .iter()
+                    .map(|value| self.masked_byte(value))
+                    .collect();
+                format!("({})", values.join("|"))
+            }
+            Element::Range { min, max } => format!(
+                "{}-{}",
+                self.masked_byte(&MaskedByte::from(*min)),
+                self.masked_byte(&MaskedByte::from(*max))
+            ),
+            Element::Not(element) => format!("!{}", self.element(element)),
+            Element::Repeat {
+                element,
+                min,
+                max,
+                greedy,
+            } => self.element(element) + &quantifier(*min, *max, *greedy),
+            Element::Group { name, elements, .. } => match name {
+                Some(name) => format!("(?<{}>{}{})", name, self.separator, self.elements(elements)),
+                None => format!("({}{})", self.separator, self.elements(elements)),
+            },
+            Element::Assertion {
+                behind,
+                negative,
+                elements,
+            } => format!(
+                "(?{}{}{}{})",
+                if *behind { "<" } else { "" },
+                if *negative { "!" } else { "=" },
+                self.separator,
+                self.elements(elements)
+            ),
+            Element::Gap { min, max } if min == max => format!("[{}]", min),
+            Element::Gap { min, max } => format!("[{}-{}]", min, max),
+            Element::Typed(typed) => typed.to_string(),
+            Element::Literal(literal) => literal.to_string(),
+            Element::Start => "^".to_string(),
+            Element::End => "$".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const PATTERNS: [&str; 11] = [
+        "0x41 0b0000?101 0x4? [2-4] 0x??",
+        "(0x40|0b1000000?) !0x00 0x20-0x7e",
+        "0x00* 0x01+? 0x4?{2,4} (0x01|0x02){3,}",
+        "u32le:0x12??56?? i16be:-5 \"a\\\"b\\n\" u\"T\"",
+        "^ ( 0x01 (?<name> 0x02 ) ) $",
+        "(?= 0x01 ) (?<! 0x02 [1-2] ) 0x03",
+        "!(0x40|0x80) 0x01-0x01",
+        "0x01 [3] 0x02{2}",
+        "",
+        "0b?1?1?1?1 0x?0",
+        "u\"😀\" \"😀\" u\"\\ud83d\\ude00\"",
+    ];
+
+    #[test]
+    fn round_trip() {
+        for formatter in &[
+            PatternFormatter::new(),
+            PatternFormatter::new().radix(Radix::Binary),
+            PatternFormatter::new().separator("\t").unwrap(),
+            PatternFormatter::new()
+                .separator("  ")
+                .and_then(|formatter| formatter.group(2, "\n"))
+                .unwrap(),
+        ] {
+            for string in &PATTERNS {
+                let pattern = Pattern::<u8>::from_str(string).unwrap();
+                let formatted = formatter.format(&pattern);
+                assert_eq!(
+                    Pattern::<u8>::from_str(&formatted).unwrap(),
+                    pattern,
+                    "{}",
+                    formatted
+                );
+            }
+        }
+
+        let pattern = Pattern::<u16>::from_str("0x12?4 0x0001-0x0fff (0x00??|0b1?)").unwrap();
+        let formatted = PatternFormatter::new().format(&pattern);
+        assert_eq!(
+            formatted,
+            "0x12?4 0x0001-0x0fff (0x00??|0b000000000000001?)"
+        );
+        assert_eq!(Pattern::<u16>::from_str(&formatted).unwrap(), pattern);
+    }
+
+    #[test]
+    fn format() {
+        let pattern = Pattern::<u8>::from_str("0x41 0b0000?101 ( 0x4? [2] ) 0x??").unwrap();
+        assert_eq!(
+            PatternFormatter::new().format(&pattern),
+            "0x41 0b0000?101 ( 0x4? [2] ) 0x??"
+        );
+        assert_eq!(
+            PatternFormatter::new()
+                .radix(Radix::Binary)
+                .format(&pattern),
+            "0b01000001 0b0000?101 ( 0b0100???? [2] ) 0b????????"
+        );
+        assert_eq!(
+            PatternFormatter::new()
+                .group(2, "\n")
+                .unwrap()
+                .format(&pattern),
+            "0x41 0b0000?101\n( 0x4? [2] ) 0x??"
+        );
+    }
+
+    #[test]
+    fn invalid_separator() {
+        for separator in &["", ",", " ,", "0x00"] {
+            assert!(matches!(
+                PatternFormatter::new().separator(separator),
+                Err(crate::Error::InvalidSeparator { .. })
+            ));
+            assert!(PatternFormatter::new().group(2, separator).is_err());
+        }
+    }
+}
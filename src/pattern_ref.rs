@@ -0,0 +1,19 @@
+use crate::{CompiledPattern, Pattern};
+
+/// Either form of a Pattern accepted by the search iterators
+pub enum PatternRef<'a, T> {
+    Pattern(&'a Pattern<T>),
+    Compiled(&'a CompiledPattern<T>),
+}
+
+impl<'a, T> From<&'a Pattern<T>> for PatternRef<'a, T> {
+    fn from(pattern: &'a Pattern<T>) -> Self {
+        PatternRef::Pattern(pattern)
+    }
+}
+
+impl<'a, T> From<&'a CompiledPattern<T>> for PatternRef<'a, T> {
+    fn from(pattern: &'a CompiledPattern<T>) -> Self {
+        PatternRef::Compiled(pattern)
+    }
+}
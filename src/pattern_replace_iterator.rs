@@ -49,10 +49,12 @@ where
             return Some(Ok(result));
         }
 
-        match self.iter.next() {
-            Some(search_type) => Some(self.replacer.handle_next(search_type)),
-            None => None,
+        for search_type in &mut self.iter {
+            if let Some(result) = self.replacer.handle_next(search_type) {
+                return Some(result);
+            }
         }
+        None
     }
 }
 
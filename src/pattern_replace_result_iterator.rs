@@ -50,15 +50,21 @@ where
             return Some(Ok(result));
         }
 
-        match self.iter.next() {
-            Some(result) => match result {
-                Ok(search_type) => Some(self.replacer.handle_next(search_type)),
-                Err(error) => Some(Err(crate::Error::IteratorError {
-                    source: error.into(),
-                })),
-            },
-            None => None,
+        for result in &mut self.iter {
+            match result {
+                Ok(search_type) => {
+                    if let Some(result) = self.replacer.handle_next(search_type) {
+                        return Some(result);
+                    }
+                }
+                Err(error) => {
+                    return Some(Err(crate::Error::IteratorError {
+                        source: error.into(),
+                    }))
+                }
+            }
         }
+        None
     }
 }
 
@@ -1,4 +1,4 @@
-use crate::{Pattern, PatternSearchType, Searcher};
+use crate::{PatternRef, PatternSearchType, PatternSet, Searcher};
 use std::fmt;
 use std::iter::Iterator;
 use std::ops;
@@ -22,12 +22,47 @@ where
         + ops::BitOrAssign
         + ops::BitAndAssign,
 {
-    pub fn new(iter: I, pattern: &'a Pattern<T>) -> Self {
+    pub fn new<P: Into<PatternRef<'a, T>>>(iter: I, pattern: P) -> Self {
         Self {
             iter,
-            searcher: Searcher::new(pattern),
+            searcher: Searcher::from_ref(pattern.into()),
         }
     }
+
+    pub fn new_overlapping<P: Into<PatternRef<'a, T>>>(iter: I, pattern: P) -> Self {
+        let mut searcher = Searcher::from_ref(pattern.into());
+        searcher.set_overlapping(true);
+        Self { iter, searcher }
+    }
+
+    pub fn new_approx<P: Into<PatternRef<'a, T>>>(iter: I, pattern: P, budget: usize) -> Self {
+        let mut searcher = Searcher::from_ref(pattern.into());
+        searcher.set_budget(budget);
+        Self { iter, searcher }
+    }
+
+    pub fn new_set(iter: I, patterns: &'a PatternSet<T>) -> Self {
+        Self {
+            iter,
+            searcher: Searcher::new(patterns.as_slice()),
+        }
+    }
+
+    /// Only reports matches starting at stream offsets where offset % modulus is
+    /// remainder, like aligned(4, 0) for 4 byte aligned code. A modulus of 0 means
+    /// no alignment.
+    pub fn aligned(mut self, modulus: usize, remainder: usize) -> Self {
+        self.searcher.set_alignment(modulus, remainder);
+        self
+    }
+
+    /// Only reports matches lying completely within the stream offsets of range,
+    /// like window(0x8000..0x20000). Other bytes are passed through unchanged and
+    /// reported offsets stay absolute.
+    pub fn window<R: ops::RangeBounds<usize>>(mut self, range: R) -> Self {
+        self.searcher.set_window(range);
+        self
+    }
 }
 
 impl<'a, I, T> Iterator for PatternSearchIterator<'a, I, T>
@@ -51,13 +86,13 @@ where
             return Some(result);
         }
 
-        while let Some(byte) = self.iter.next() {
+        for byte in &mut self.iter {
             if let Some(result) = self.searcher.handle_next(byte) {
                 return Some(result);
             }
         }
 
-        self.searcher.handle_existing_data()
+        self.searcher.handle_end()
     }
 }
 
@@ -74,7 +109,32 @@ where
         + ops::BitOrAssign
         + ops::BitAndAssign,
 {
-    fn search_pattern(self, pattern: &'a Pattern<T>) -> PatternSearchIterator<'a, Self, T>;
+    /// Reports matches of pattern and the bytes between them. A match spans at
+    /// most MAX_MATCH_LEN bytes.
+    fn search_pattern<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchIterator<'a, Self, T>;
+
+    /// Reports a match at every start offset, so matches can share bytes. Every
+    /// input byte is still passed on once: bytes already contained in a reported
+    /// match are not reported again.
+    fn search_pattern_overlapping<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchIterator<'a, Self, T>;
+
+    /// Approximate search where up to budget elements of the Pattern may not match
+    /// their byte. The positions of those elements are reported as mismatches.
+    fn search_pattern_approx<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+        budget: usize,
+    ) -> PatternSearchIterator<'a, Self, T>;
+
+    /// Searches for all Patterns of the set in a single pass. The id of a match is
+    /// the position of the matching Pattern in the set.
+    fn search_patterns(self, patterns: &'a PatternSet<T>) -> PatternSearchIterator<'a, Self, T>;
 }
 
 impl<'a, I, T> PatternSearchExt<'a, T> for I
@@ -91,33 +151,62 @@ where
         + ops::BitOrAssign
         + ops::BitAndAssign,
 {
-    fn search_pattern(self, pattern: &'a Pattern<T>) -> PatternSearchIterator<'a, Self, T> {
+    fn search_pattern<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchIterator<'a, Self, T> {
         PatternSearchIterator::new(self, pattern)
     }
+
+    fn search_pattern_overlapping<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchIterator<'a, Self, T> {
+        PatternSearchIterator::new_overlapping(self, pattern)
+    }
+
+    fn search_pattern_approx<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+        budget: usize,
+    ) -> PatternSearchIterator<'a, Self, T> {
+        PatternSearchIterator::new_approx(self, pattern, budget)
+    }
+
+    fn search_patterns(self, patterns: &'a PatternSet<T>) -> PatternSearchIterator<'a, Self, T> {
+        PatternSearchIterator::new_set(self, patterns)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{Captures, Pattern};
     use std::str::FromStr;
 
     #[test]
     fn search_global() {
         let d = [0x1a, 0x2b, 0x3c, 0x4d, 0x5e, 0x6f];
         let s = Pattern::<u8>::from_str("0b???0???? 0b???1????").unwrap();
-        let mut iter = d.to_vec().into_iter().search_pattern(&s);
+        let mut iter = d.iter().copied().search_pattern(&s);
         assert_eq!(Some(PatternSearchType::NonMatch(0x1a)), iter.next());
         assert_eq!(
             Some(PatternSearchType::Match {
                 data: [0x2b, 0x3c].to_vec(),
-                index: 1
+                index: 1,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
             }),
             iter.next()
         );
         assert_eq!(
             Some(PatternSearchType::Match {
                 data: [0x4d, 0x5e].to_vec(),
-                index: 3
+                index: 3,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
             }),
             iter.next()
         );
@@ -125,4 +214,148 @@ mod tests {
         assert_eq!(Some(PatternSearchType::NonMatch(0x6f)), iter.next());
         assert_eq!(None, iter.next());
     }
+
+    #[test]
+    fn search_restart() {
+        let d = [0x41, 0x41, 0x41, 0x42];
+        let s = Pattern::<u8>::from_str("0x41 0x41 0x42").unwrap();
+        let mut iter = d.iter().copied().search_pattern(&s);
+        assert_eq!(Some(PatternSearchType::NonMatch(0x41)), iter.next());
+        assert_eq!(
+            Some(PatternSearchType::Match {
+                data: [0x41, 0x41, 0x42].to_vec(),
+                index: 1,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
+            }),
+            iter.next()
+        );
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn search_overlapping() {
+        let d = [0x41, 0x41, 0x41];
+        let s = Pattern::<u8>::from_str("0x41 0x41").unwrap();
+        let mut iter = d.iter().copied().search_pattern_overlapping(&s);
+        assert_eq!(
+            Some(PatternSearchType::Match {
+                data: [0x41, 0x41].to_vec(),
+                index: 0,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
+            }),
+            iter.next()
+        );
+        assert_eq!(
+            Some(PatternSearchType::Match {
+                data: [0x41, 0x41].to_vec(),
+                index: 1,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
+            }),
+            iter.next()
+        );
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn search_aligned() {
+        let d = [0x00, 0x46, 0xc0, 0x46, 0xc0, 0x00];
+        let s = Pattern::<u8>::from_str("0x46 0xc0").unwrap();
+        let found: Vec<usize> = d
+            .iter()
+            .copied()
+            .search_pattern(&s.compile())
+            .aligned(2, 1)
+            .filter_map(|result| match result {
+                PatternSearchType::Match { index, .. } => Some(index),
+                PatternSearchType::NonMatch(_) => None,
+            })
+            .collect();
+        assert_eq!(found, [1, 3]);
+
+        let found = d
+            .iter()
+            .copied()
+            .search_pattern(&s)
+            .aligned(4, 0)
+            .filter(|result| matches!(result, PatternSearchType::Match { .. }))
+            .count();
+        assert_eq!(found, 0);
+    }
+
+    #[test]
+    fn search_window() {
+        let d = [0x46, 0xc0, 0x00, 0x46, 0xc0, 0x46, 0xc0];
+        let s = Pattern::<u8>::from_str("0x46 0xc0").unwrap();
+        let results: Vec<PatternSearchType<u8>> =
+            d.iter().copied().search_pattern(&s).window(1..6).collect();
+        assert_eq!(results.len(), 6);
+        assert_eq!(
+            results[3],
+            PatternSearchType::Match {
+                data: [0x46, 0xc0].to_vec(),
+                index: 3,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn search_approx() {
+        let d = [0x1a, 0x2b, 0x3c, 0x4d];
+        let s = Pattern::<u8>::from_str("0x2b 0x3d 0x4d").unwrap();
+        let mut iter = d.iter().copied().search_pattern_approx(&s, 1);
+        assert_eq!(Some(PatternSearchType::NonMatch(0x1a)), iter.next());
+        assert_eq!(
+            Some(PatternSearchType::Match {
+                data: [0x2b, 0x3c, 0x4d].to_vec(),
+                index: 1,
+                id: 0,
+                mismatches: vec![1],
+                captures: Captures::default(),
+            }),
+            iter.next()
+        );
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn search_long_run() {
+        // Each byte continuing the run of a greedy repeat is only checked once
+        let d = vec![0x41; 200_000];
+        let s = Pattern::<u8>::from_str("0x41 0x??*").unwrap();
+        let found: Vec<(usize, usize)> = d
+            .iter()
+            .copied()
+            .search_pattern(&s)
+            .filter_map(|result| match result {
+                PatternSearchType::Match { data, index, .. } => Some((index, data.len())),
+                PatternSearchType::NonMatch(_) => None,
+            })
+            .collect();
+        assert_eq!(found, [(0, d.len())]);
+
+        // Runs longer than MAX_MATCH_LEN are split
+        let d = vec![0x41; crate::MAX_MATCH_LEN + 10];
+        let found: Vec<(usize, usize)> = d
+            .iter()
+            .copied()
+            .search_pattern(&s)
+            .filter_map(|result| match result {
+                PatternSearchType::Match { data, index, .. } => Some((index, data.len())),
+                PatternSearchType::NonMatch(_) => None,
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [(0, crate::MAX_MATCH_LEN), (crate::MAX_MATCH_LEN, 10)]
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{Pattern, PatternSearchType, Searcher};
+use crate::{PatternRef, PatternSearchType, PatternSet, Searcher};
 use std::fmt;
 use std::iter::Iterator;
 use std::ops;
@@ -22,12 +22,47 @@ where
         + ops::BitOrAssign
         + ops::BitAndAssign,
 {
-    pub fn new(iter: I, pattern: &'a Pattern<T>) -> Self {
+    pub fn new<P: Into<PatternRef<'a, T>>>(iter: I, pattern: P) -> Self {
         Self {
             iter,
-            searcher: Searcher::new(pattern),
+            searcher: Searcher::from_ref(pattern.into()),
         }
     }
+
+    pub fn new_overlapping<P: Into<PatternRef<'a, T>>>(iter: I, pattern: P) -> Self {
+        let mut searcher = Searcher::from_ref(pattern.into());
+        searcher.set_overlapping(true);
+        Self { iter, searcher }
+    }
+
+    pub fn new_approx<P: Into<PatternRef<'a, T>>>(iter: I, pattern: P, budget: usize) -> Self {
+        let mut searcher = Searcher::from_ref(pattern.into());
+        searcher.set_budget(budget);
+        Self { iter, searcher }
+    }
+
+    pub fn new_set(iter: I, patterns: &'a PatternSet<T>) -> Self {
+        Self {
+            iter,
+            searcher: Searcher::new(patterns.as_slice()),
+        }
+    }
+
+    /// Only reports matches starting at stream offsets where offset % modulus is
+    /// remainder, like aligned(4, 0) for 4 byte aligned code. A modulus of 0 means
+    /// no alignment.
+    pub fn aligned(mut self, modulus: usize, remainder: usize) -> Self {
+        self.searcher.set_alignment(modulus, remainder);
+        self
+    }
+
+    /// Only reports matches lying completely within the stream offsets of range,
+    /// like window(0x8000..0x20000). Other bytes are passed through unchanged and
+    /// reported offsets stay absolute.
+    pub fn window<R: ops::RangeBounds<usize>>(mut self, range: R) -> Self {
+        self.searcher.set_window(range);
+        self
+    }
 }
 
 impl<'a, 'b, I, T> Iterator for PatternSearchRefIterator<'a, I, T>
@@ -52,13 +87,13 @@ where
             return Some(result);
         }
 
-        while let Some(byte) = self.iter.next() {
+        for byte in &mut self.iter {
             if let Some(result) = self.searcher.handle_next(*byte) {
                 return Some(result);
             }
         }
 
-        self.searcher.handle_existing_data()
+        self.searcher.handle_end()
     }
 }
 
@@ -76,7 +111,30 @@ where
         + ops::BitOrAssign
         + ops::BitAndAssign,
 {
-    fn search_pattern(self, pattern: &'a Pattern<T>) -> PatternSearchRefIterator<'a, Self, T>;
+    fn search_pattern<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchRefIterator<'a, Self, T>;
+
+    /// Reports a match at every start offset, so matches can share bytes. Every
+    /// input byte is still passed on once: bytes already contained in a reported
+    /// match are not reported again.
+    fn search_pattern_overlapping<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchRefIterator<'a, Self, T>;
+
+    /// Approximate search where up to budget elements of the Pattern may not match
+    /// their byte. The positions of those elements are reported as mismatches.
+    fn search_pattern_approx<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+        budget: usize,
+    ) -> PatternSearchRefIterator<'a, Self, T>;
+
+    /// Searches for all Patterns of the set in a single pass. The id of a match is
+    /// the position of the matching Pattern in the set.
+    fn search_patterns(self, patterns: &'a PatternSet<T>) -> PatternSearchRefIterator<'a, Self, T>;
 }
 
 impl<'a, 'b, I, T> PatternSearchRefExt<'a, 'b, T> for I
@@ -94,14 +152,37 @@ where
         + ops::BitOrAssign
         + ops::BitAndAssign,
 {
-    fn search_pattern(self, pattern: &'a Pattern<T>) -> PatternSearchRefIterator<'a, Self, T> {
+    fn search_pattern<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchRefIterator<'a, Self, T> {
         PatternSearchRefIterator::new(self, pattern)
     }
+
+    fn search_pattern_overlapping<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchRefIterator<'a, Self, T> {
+        PatternSearchRefIterator::new_overlapping(self, pattern)
+    }
+
+    fn search_pattern_approx<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+        budget: usize,
+    ) -> PatternSearchRefIterator<'a, Self, T> {
+        PatternSearchRefIterator::new_approx(self, pattern, budget)
+    }
+
+    fn search_patterns(self, patterns: &'a PatternSet<T>) -> PatternSearchRefIterator<'a, Self, T> {
+        PatternSearchRefIterator::new_set(self, patterns)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{Captures, Pattern};
     use std::str::FromStr;
 
     #[test]
@@ -113,14 +194,20 @@ mod tests {
         assert_eq!(
             Some(PatternSearchType::Match {
                 data: [0x2b, 0x3c].to_vec(),
-                index: 1
+                index: 1,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
             }),
             iter.next()
         );
         assert_eq!(
             Some(PatternSearchType::Match {
                 data: [0x4d, 0x5e].to_vec(),
-                index: 3
+                index: 3,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
             }),
             iter.next()
         );
@@ -128,4 +215,56 @@ mod tests {
         assert_eq!(Some(PatternSearchType::NonMatch(0x6f)), iter.next());
         assert_eq!(None, iter.next());
     }
+
+    #[test]
+    fn search_restart() {
+        let d = [0x41, 0x41, 0x41, 0x42];
+        let s = Pattern::<u8>::from_str("0x41 0x41 0x42").unwrap();
+        let mut iter = d.iter().search_pattern(&s);
+        assert_eq!(Some(PatternSearchType::NonMatch(0x41)), iter.next());
+        assert_eq!(
+            Some(PatternSearchType::Match {
+                data: [0x41, 0x41, 0x42].to_vec(),
+                index: 1,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
+            }),
+            iter.next()
+        );
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn search_set() {
+        let d = [0x1a, 0x2b, 0x3c, 0x4d];
+        let set: PatternSet<u8> = vec![
+            Pattern::from_str("0x3c 0x4d").unwrap(),
+            Pattern::from_str("0x2b").unwrap(),
+        ]
+        .into();
+        let mut iter = d.iter().search_patterns(&set);
+        assert_eq!(Some(PatternSearchType::NonMatch(0x1a)), iter.next());
+        assert_eq!(
+            Some(PatternSearchType::Match {
+                data: [0x2b].to_vec(),
+                index: 1,
+                id: 1,
+                mismatches: vec![],
+                captures: Captures::default(),
+            }),
+            iter.next()
+        );
+        assert_eq!(
+            Some(PatternSearchType::Match {
+                data: [0x3c, 0x4d].to_vec(),
+                index: 2,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
+            }),
+            iter.next()
+        );
+        assert_eq!(None, iter.next());
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{Pattern, PatternSearchType, Searcher};
+use crate::{PatternRef, PatternSearchType, PatternSet, Searcher};
 use std::fmt;
 use std::iter::Iterator;
 use std::ops;
@@ -22,12 +22,47 @@ where
         + ops::BitOrAssign
         + ops::BitAndAssign,
 {
-    pub fn new(iter: I, pattern: &'a Pattern<T>) -> Self {
+    pub fn new<P: Into<PatternRef<'a, T>>>(iter: I, pattern: P) -> Self {
         Self {
             iter,
-            searcher: Searcher::new(pattern),
+            searcher: Searcher::from_ref(pattern.into()),
         }
     }
+
+    pub fn new_overlapping<P: Into<PatternRef<'a, T>>>(iter: I, pattern: P) -> Self {
+        let mut searcher = Searcher::from_ref(pattern.into());
+        searcher.set_overlapping(true);
+        Self { iter, searcher }
+    }
+
+    pub fn new_approx<P: Into<PatternRef<'a, T>>>(iter: I, pattern: P, budget: usize) -> Self {
+        let mut searcher = Searcher::from_ref(pattern.into());
+        searcher.set_budget(budget);
+        Self { iter, searcher }
+    }
+
+    pub fn new_set(iter: I, patterns: &'a PatternSet<T>) -> Self {
+        Self {
+            iter,
+            searcher: Searcher::new(patterns.as_slice()),
+        }
+    }
+
+    /// Only reports matches starting at stream offsets where offset % modulus is
+    /// remainder, like aligned(4, 0) for 4 byte aligned code. A modulus of 0 means
+    /// no alignment.
+    pub fn aligned(mut self, modulus: usize, remainder: usize) -> Self {
+        self.searcher.set_alignment(modulus, remainder);
+        self
+    }
+
+    /// Only reports matches lying completely within the stream offsets of range,
+    /// like window(0x8000..0x20000). Other bytes are passed through unchanged and
+    /// reported offsets stay absolute.
+    pub fn window<R: ops::RangeBounds<usize>>(mut self, range: R) -> Self {
+        self.searcher.set_window(range);
+        self
+    }
 }
 
 impl<'a, I, E, T> Iterator for PatternSearchResultIterator<'a, I, T>
@@ -52,7 +87,7 @@ where
             return Some(Ok(result));
         }
 
-        while let Some(result) = self.iter.next() {
+        for result in &mut self.iter {
             match result {
                 Ok(byte) => {
                     if let Some(result) = self.searcher.handle_next(byte) {
@@ -65,10 +100,7 @@ where
             }
         }
 
-        match self.searcher.handle_existing_data() {
-            Some(result) => Some(Ok(result)),
-            None => None,
-        }
+        self.searcher.handle_end().map(Ok)
     }
 }
 
@@ -86,7 +118,33 @@ where
         + ops::BitOrAssign
         + ops::BitAndAssign,
 {
-    fn search_pattern(self, pattern: &'a Pattern<T>) -> PatternSearchResultIterator<'a, Self, T>;
+    fn search_pattern<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchResultIterator<'a, Self, T>;
+
+    /// Reports a match at every start offset, so matches can share bytes. Every
+    /// input byte is still passed on once: bytes already contained in a reported
+    /// match are not reported again.
+    fn search_pattern_overlapping<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchResultIterator<'a, Self, T>;
+
+    /// Approximate search where up to budget elements of the Pattern may not match
+    /// their byte. The positions of those elements are reported as mismatches.
+    fn search_pattern_approx<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+        budget: usize,
+    ) -> PatternSearchResultIterator<'a, Self, T>;
+
+    /// Searches for all Patterns of the set in a single pass. The id of a match is
+    /// the position of the matching Pattern in the set.
+    fn search_patterns(
+        self,
+        patterns: &'a PatternSet<T>,
+    ) -> PatternSearchResultIterator<'a, Self, T>;
 }
 
 impl<'a, I, E, T> PatternSearchResultExt<'a, E, T> for I
@@ -104,14 +162,40 @@ where
         + ops::BitOrAssign
         + ops::BitAndAssign,
 {
-    fn search_pattern(self, pattern: &'a Pattern<T>) -> PatternSearchResultIterator<'a, Self, T> {
+    fn search_pattern<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchResultIterator<'a, Self, T> {
         PatternSearchResultIterator::new(self, pattern)
     }
+
+    fn search_pattern_overlapping<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+    ) -> PatternSearchResultIterator<'a, Self, T> {
+        PatternSearchResultIterator::new_overlapping(self, pattern)
+    }
+
+    fn search_pattern_approx<P: Into<PatternRef<'a, T>>>(
+        self,
+        pattern: P,
+        budget: usize,
+    ) -> PatternSearchResultIterator<'a, Self, T> {
+        PatternSearchResultIterator::new_approx(self, pattern, budget)
+    }
+
+    fn search_patterns(
+        self,
+        patterns: &'a PatternSet<T>,
+    ) -> PatternSearchResultIterator<'a, Self, T> {
+        PatternSearchResultIterator::new_set(self, patterns)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{Captures, Pattern};
     use std::io::Read;
     use std::str::FromStr;
 
@@ -127,14 +211,20 @@ mod tests {
         assert_eq!(
             PatternSearchType::Match {
                 data: [0x2b, 0x3c].to_vec(),
-                index: 1
+                index: 1,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
             },
             iter.next().unwrap().unwrap()
         );
         assert_eq!(
             PatternSearchType::Match {
                 data: [0x4d, 0x5e].to_vec(),
-                index: 3
+                index: 3,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
             },
             iter.next().unwrap().unwrap()
         );
@@ -145,4 +235,54 @@ mod tests {
         );
         assert!(iter.next().is_none());
     }
+
+    #[test]
+    fn search_restart() {
+        let d = [0x41, 0x41, 0x41, 0x42];
+        let s = Pattern::<u8>::from_str("0x41 0x41 0x42").unwrap();
+        let mut iter = d.bytes().search_pattern(&s);
+        assert_eq!(
+            PatternSearchType::NonMatch(0x41),
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(
+            PatternSearchType::Match {
+                data: [0x41, 0x41, 0x42].to_vec(),
+                index: 1,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
+            },
+            iter.next().unwrap().unwrap()
+        );
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn search_compiled() {
+        let d = [0x1a, 0x2b, 0x3c, 0x4d, 0x5e, 0x6f];
+        let s = Pattern::<u8>::from_str("0x4d 0x5e").unwrap().compile();
+        let mut iter = d.bytes().search_pattern(&s);
+        for byte in &[0x1a, 0x2b, 0x3c] {
+            assert_eq!(
+                PatternSearchType::NonMatch(*byte),
+                iter.next().unwrap().unwrap()
+            );
+        }
+        assert_eq!(
+            PatternSearchType::Match {
+                data: [0x4d, 0x5e].to_vec(),
+                index: 3,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
+            },
+            iter.next().unwrap().unwrap()
+        );
+        assert_eq!(
+            PatternSearchType::NonMatch(0x6f),
+            iter.next().unwrap().unwrap()
+        );
+        assert!(iter.next().is_none());
+    }
 }
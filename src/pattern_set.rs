@@ -0,0 +1,76 @@
+use crate::Pattern;
+use std::iter::FromIterator;
+
+/// PatternSet is a container of Patterns which are searched for at the same time.
+/// Patterns are looked up by the first byte of their matches, so at each offset
+/// only Patterns which can start with the byte there are tried.
+/// A Pattern is identified by its position in the set, which is reported as id of a
+/// match. If several Patterns match at the same offset the one added first wins.
+#[derive(Default)]
+pub struct PatternSet<T> {
+    patterns: Vec<Pattern<T>>,
+}
+
+impl<T> PatternSet<T> {
+    pub fn new() -> Self {
+        Self {
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Adds a Pattern and returns its id
+    pub fn push(&mut self, pattern: Pattern<T>) -> usize {
+        self.patterns.push(pattern);
+        self.patterns.len() - 1
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Pattern<T>> {
+        self.patterns.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Pattern<T>> {
+        self.patterns.iter()
+    }
+
+    pub(crate) fn as_slice(&self) -> &[Pattern<T>] {
+        &self.patterns
+    }
+}
+
+impl<T> From<Vec<Pattern<T>>> for PatternSet<T> {
+    fn from(patterns: Vec<Pattern<T>>) -> Self {
+        Self { patterns }
+    }
+}
+
+impl<T> FromIterator<Pattern<T>> for PatternSet<T> {
+    fn from_iter<I: IntoIterator<Item = Pattern<T>>>(iter: I) -> Self {
+        Self {
+            patterns: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn ids() {
+        let mut set = PatternSet::new();
+        assert_eq!(set.push(Pattern::<u8>::from_str("0x01").unwrap()), 0);
+        assert_eq!(set.push(Pattern::<u8>::from_str("0x02 0x03").unwrap()), 1);
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.get(1).unwrap().element_count(), 2);
+        assert!(set.get(2).is_none());
+    }
+}
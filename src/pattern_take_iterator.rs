@@ -28,10 +28,12 @@ where
             return Some(PatternSearchType::NonMatch(byte));
         }
 
-        match self.iter.next() {
-            Some(search_type) => Some(self.taker.handle_next(search_type)),
-            None => None,
+        for search_type in &mut self.iter {
+            if let Some(result) = self.taker.handle_next(search_type) {
+                return Some(result);
+            }
         }
+        None
     }
 }
 
@@ -29,13 +29,17 @@ where
             return Some(Ok(PatternSearchType::NonMatch(byte)));
         }
 
-        match self.iter.next() {
-            Some(result) => match result {
-                Ok(search_type) => Some(Ok(self.taker.handle_next(search_type))),
-                Err(error) => Some(Err(error)),
-            },
-            None => None,
+        for result in &mut self.iter {
+            match result {
+                Ok(search_type) => {
+                    if let Some(result) = self.taker.handle_next(search_type) {
+                        return Some(Ok(result));
+                    }
+                }
+                Err(error) => return Some(Err(error)),
+            }
         }
+        None
     }
 }
 
@@ -0,0 +1,53 @@
+/// Tracks the stream offset of the next byte not yet passed on. Matches of an
+/// overlapping search can start before this offset.
+pub struct Position {
+    next: usize,
+}
+
+impl Position {
+    pub fn new() -> Self {
+        Self { next: 0 }
+    }
+
+    /// A single byte was passed on.
+    pub fn non_match(&mut self) {
+        self.next += 1;
+    }
+
+    /// Returns true if a match starting at index overlaps bytes already passed on.
+    pub fn overlaps(&self, index: usize) -> bool {
+        index < self.next
+    }
+
+    /// A match was passed on as a whole.
+    pub fn advance(&mut self, index: usize, len: usize) {
+        self.next = self.next.max(index + len);
+    }
+
+    /// Returns the bytes of a match which were not already passed on as part of
+    /// an earlier overlapping match.
+    pub fn unseen<T>(&mut self, mut data: Vec<T>, index: usize) -> Vec<T> {
+        let seen = self.next.saturating_sub(index).min(data.len());
+        self.advance(index, data.len());
+        data.drain(..seen);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_matches() {
+        let mut position = Position::new();
+        position.non_match();
+        assert!(!position.overlaps(1));
+        assert_eq!(position.unseen(vec![1, 2, 3], 1), [1, 2, 3]);
+        assert!(position.overlaps(2));
+        assert_eq!(position.unseen(vec![2, 3, 4], 2), [4]);
+        assert_eq!(position.unseen(vec![3], 3), []);
+        position.non_match();
+        assert!(!position.overlaps(6));
+    }
+}
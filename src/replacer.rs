@@ -1,10 +1,11 @@
-use crate::{OptFifo, Pattern, PatternSearchType};
+use crate::{OptFifo, Pattern, PatternSearchType, Position};
 use std::fmt;
 use std::ops;
 
 pub struct Replacer<'a, T> {
     pattern: &'a Pattern<T>,
     data: OptFifo<T>,
+    position: Position,
 }
 
 impl<'a, T> Replacer<'a, T>
@@ -24,6 +25,7 @@ where
         Self {
             pattern,
             data: OptFifo::new(),
+            position: Position::new(),
         }
     }
 
@@ -34,18 +36,29 @@ where
     }
 
     // Handles PatternSearchType by returning a single byte or an error that
-    // happened in the replace process
-    pub fn handle_next(&mut self, search_type: PatternSearchType<T>) -> crate::Result<T> {
-        match search_type {
-            PatternSearchType::NonMatch(byte) => Ok(byte),
-            PatternSearchType::Match { data, index: _ } => match self.pattern.replace(data) {
-                Ok(mut item) => {
-                    let byte = item.remove(0);
-                    self.data.set(item);
-                    Ok(byte)
+    // happened in the replace process. Returns None if nothing is left to return.
+    // A match overlapping an earlier match (see overlapping search) is not replaced,
+    // only its bytes not already returned are passed on unchanged.
+    pub fn handle_next(&mut self, search_type: PatternSearchType<T>) -> Option<crate::Result<T>> {
+        let data = match search_type {
+            PatternSearchType::NonMatch(byte) => {
+                self.position.non_match();
+                return Some(Ok(byte));
+            }
+            PatternSearchType::Match { data, index, .. } => {
+                if self.position.overlaps(index) {
+                    self.position.unseen(data, index)
+                } else {
+                    self.position.advance(index, data.len());
+                    match self.pattern.replace(data) {
+                        Ok(item) => item,
+                        Err(error) => return Some(Err(error)),
+                    }
                 }
-                Err(error) => Err(error),
-            },
-        }
+            }
+        };
+
+        self.data.set(data);
+        self.data.get().map(Ok)
     }
 }
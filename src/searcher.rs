@@ -1,13 +1,91 @@
-use crate::{Pattern, PatternSearchType};
+use crate::matcher::{OpenRun, Outcome};
+use crate::{Captures, CompiledPattern, Element, Pattern, PatternRef, PatternSearchType};
+use std::collections::VecDeque;
 use std::fmt;
-use std::mem;
 use std::ops;
+use std::ops::Range;
+
+/// Most bytes a match of the search iterators spans. Possible matches are buffered
+/// until they are decided, so an unlimited repeat like 0x00* would otherwise
+/// buffer all input as long as its run continues. Once this many bytes are
+/// buffered, repeats take no further bytes and end anchors or lookahead assertions
+/// waiting for the bytes after them fail.
+pub const MAX_MATCH_LEN: usize = 1 << 20;
+
+// A full match of a pattern at the start of the buffered data
+struct Found {
+    id: usize,
+    len: usize,
+    mismatches: Vec<usize>,
+    captures: Vec<Option<Range<usize>>>,
+}
+
+// Outcome of matching the buffered data against the patterns
+enum Status {
+    // Buffered data starts with these full matches
+    Match(Vec<Found>),
+
+    // Buffered data is a prefix of a possible match
+    Partial,
+
+    // No match can start at this count of leading buffered bytes
+    Fail(usize),
+}
 
 pub struct Searcher<'a, T> {
-    pattern: &'a Pattern<T>,
-    matched: bool,
+    // Patterns tried at each start in order, index is the id of a pattern
+    patterns: &'a [Pattern<T>],
+
+    // Set if a single compiled pattern of single byte elements is searched for
+    compiled: Option<&'a CompiledPattern<T>>,
+
+    // Bytes of a possible match, first byte is at stream offset start. It is
+    // preceded by the last kept bytes before start within the window, which are
+    // seen by lookbehind assertions only. Up to behind bytes are kept.
     data: Vec<T>,
-    taken: usize,
+    start: usize,
+    kept: usize,
+    behind: usize,
+
+    // Ids of the patterns not yet known to not match at start, in order
+    candidates: Vec<usize>,
+
+    // Element the first byte of a match of each pattern needs to match, and ids of
+    // the patterns whose first element matches each byte value. Only those are
+    // tried at a start.
+    first: Vec<Option<&'a Element<T>>>,
+    table: Vec<Vec<usize>>,
+
+    // Patterns of single byte elements are checked incrementally: count of
+    // leading bytes at start already checked against them and positions of
+    // their mismatches so far
+    simple: Vec<bool>,
+    checked: usize,
+    mismatches: Vec<Vec<usize>>,
+
+    // Open greedy repeats other patterns wait on and count of leading bytes at
+    // start checked for them
+    runs: Vec<Option<(OpenRun<'a, T>, usize)>>,
+
+    // Count of mismatching elements a match may contain
+    budget: usize,
+
+    // Results waiting to be handed out
+    results: VecDeque<PatternSearchType<T>>,
+
+    // Every start offset is reported, matches can share bytes
+    overlapping: bool,
+
+    // Stream offset up to which bytes were handed out as part of a match
+    covered: usize,
+
+    // Matches only start at offsets with this remainder modulo modulus
+    modulus: usize,
+    remainder: usize,
+
+    // Matches only lie within stream offsets window_start to window_end (exclusive)
+    window_start: usize,
+    window_end: Option<usize>,
 }
 
 impl<'a, T> Searcher<'a, T>
@@ -23,45 +101,1080 @@ where
         + ops::BitOrAssign
         + ops::BitAndAssign,
 {
-    pub fn new(pattern: &'a Pattern<T>) -> Self {
-        let capacity = pattern.len();
+    pub fn new(patterns: &'a [Pattern<T>]) -> Self {
+        let first: Vec<_> = patterns.iter().map(Pattern::first).collect();
         Self {
-            pattern,
-            matched: false,
-            data: Vec::with_capacity(capacity),
-            taken: 0,
+            patterns,
+            compiled: None,
+            data: Vec::new(),
+            start: 0,
+            kept: 0,
+            behind: patterns.iter().map(Pattern::behind).max().unwrap_or(0),
+            candidates: Vec::new(),
+            table: Self::table(&first),
+            first,
+            simple: patterns.iter().map(Pattern::is_simple).collect(),
+            checked: 0,
+            mismatches: vec![Vec::new(); patterns.len()],
+            runs: vec![None; patterns.len()],
+            budget: 0,
+            results: VecDeque::new(),
+            overlapping: false,
+            covered: 0,
+            modulus: 1,
+            remainder: 0,
+            window_start: 0,
+            window_end: None,
         }
     }
 
-    // Handles remaining data of a partial match
-    pub fn handle_existing_data(&mut self) -> Option<PatternSearchType<T>> {
-        if !self.data.is_empty() {
-            let byte = self.data.remove(0);
-            return Some(PatternSearchType::NonMatch(byte));
+    // Returns ids of the patterns whose first element matches value, patterns
+    // without first element match every value
+    fn starting_with(first: &[Option<&'a Element<T>>], value: T) -> Vec<usize> {
+        (0..first.len())
+            .filter(|id| first[*id].is_none_or(|first| first.matches(value)))
+            .collect()
+    }
+
+    // Returns the patterns starting with each byte value
+    fn table(first: &[Option<&'a Element<T>>]) -> Vec<Vec<usize>> {
+        (0..=u8::MAX)
+            .map(|value| Self::starting_with(first, From::from(value)))
+            .collect()
+    }
+
+    pub fn from_ref(pattern: PatternRef<'a, T>) -> Self {
+        match pattern {
+            PatternRef::Pattern(pattern) => Self::new(std::slice::from_ref(pattern)),
+            PatternRef::Compiled(compiled) => Self {
+                compiled: Some(compiled).filter(|compiled| compiled.pattern().is_simple()),
+                ..Self::new(std::slice::from_ref(compiled.pattern()))
+            },
         }
-        None
+    }
+
+    // In overlapping mode a match is reported at every start offset. Bytes are still
+    // handed out only once: a byte which already was part of a reported match is not
+    // reported again as NonMatch.
+    pub fn set_overlapping(&mut self, overlapping: bool) {
+        self.overlapping = overlapping;
+    }
+
+    // A match may contain up to budget elements not matching their byte
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+    }
+
+    // Matches only start at stream offsets where offset % modulus == remainder.
+    // A modulus of 0 or 1 allows every offset.
+    pub fn set_alignment(&mut self, modulus: usize, remainder: usize) {
+        self.modulus = modulus.max(1);
+        self.remainder = remainder % self.modulus;
+    }
+
+    // Matches only lie within the stream offsets of range, bytes outside of it are
+    // handed out as NonMatch. Anchors match at the bounds of the window.
+    pub fn set_window<R: ops::RangeBounds<usize>>(&mut self, range: R) {
+        self.window_start = match range.start_bound() {
+            ops::Bound::Included(start) => *start,
+            ops::Bound::Excluded(start) => start.saturating_add(1),
+            ops::Bound::Unbounded => 0,
+        };
+        self.window_end = match range.end_bound() {
+            // An end past usize::MAX is no limit at all
+            ops::Bound::Included(end) => end.checked_add(1),
+            ops::Bound::Excluded(end) => Some(*end),
+            ops::Bound::Unbounded => None,
+        };
+    }
+
+    // Handles results that are already resolved
+    pub fn handle_existing_data(&mut self) -> Option<PatternSearchType<T>> {
+        self.results.pop_front()
     }
 
     // Handles next input byte. Needs to be called after handle_existing_data returns None and
     // returns None if byte is eaten as part of a possible match
     pub fn handle_next(&mut self, byte: T) -> Option<PatternSearchType<T>> {
-        self.taken += 1;
         self.data.push(byte);
+        // Nothing is decided before a window of the compiled pattern is buffered
+        if self.pending().len() >= self.skipping().map_or(1, CompiledPattern::element_count) {
+            self.resolve(false);
+        }
+        self.results.pop_front()
+    }
+
+    // Returns the compiled pattern if it skips ahead, which is only possible
+    // without mismatches
+    fn skipping(&self) -> Option<&'a CompiledPattern<T>> {
+        self.compiled.filter(|_| self.budget == 0)
+    }
+
+    // Handles end of input. Needs to be called repeatedly after input is exhausted until
+    // it returns None
+    pub fn handle_end(&mut self) -> Option<PatternSearchType<T>> {
+        self.resolve(true);
+        self.results.pop_front()
+    }
+
+    // Horspool search, waits until a full window is buffered and on a mismatch skips
+    // as many bytes as the last byte of the window allows
+    fn compiled_status(&self, compiled: &CompiledPattern<T>, data: &[T], end: bool) -> Status {
+        let len = compiled.element_count();
+
+        if len == 0 {
+            Status::Fail(1)
+        } else if data.len() < len {
+            if end {
+                Status::Fail(1)
+            } else {
+                Status::Partial
+            }
+        } else if (0..len)
+            .rev()
+            .all(|index| compiled.pattern().get(index).unwrap().matches(data[index]))
+        {
+            Status::Match(vec![Found {
+                id: 0,
+                len,
+                mismatches: Vec::new(),
+                captures: Vec::new(),
+            }])
+        } else {
+            Status::Fail(compiled.shift(data[len - 1]))
+        }
+    }
+
+    fn status(&mut self, end: bool) -> Status {
+        if self.start < self.window_start {
+            return Status::Fail(self.window_start - self.start);
+        }
+
+        // Data beyond the window is not part of any match
+        let pending = self.data.len() - self.kept;
+        let (len, end) = match self.window_end {
+            Some(window_end) if self.start >= window_end => return Status::Fail(usize::MAX),
+            Some(window_end) if pending >= window_end - self.start => {
+                (window_end - self.start, true)
+            }
+            _ => (pending, end),
+        };
+
+        // A match spans at most MAX_MATCH_LEN bytes
+        let limited = !end && len >= MAX_MATCH_LEN;
+        let (len, end) = if limited {
+            (MAX_MATCH_LEN, true)
+        } else {
+            (len, end)
+        };
+
+        // Distance to the next offset a match can start at
+        let misaligned = (self.remainder + self.modulus - self.start % self.modulus) % self.modulus;
+        if misaligned > 0 {
+            return Status::Fail(misaligned);
+        }
 
-        if self.pattern.get(self.data.len() - 1).unwrap().matches(byte) {
-            if self.data.len() == self.pattern.len() {
-                self.matched = true;
-                let mut other = Vec::with_capacity(self.pattern.len());
-                mem::swap(&mut other, &mut self.data);
-                return Some(PatternSearchType::Match {
-                    data: other,
-                    index: self.taken - self.pattern.len(),
+        // Lookbehind assertions see the kept bytes in front of data
+        let (joined, origin) = (&self.data[..self.kept + len], self.kept);
+        let data = &joined[origin..];
+
+        if let Some(compiled) = self.skipping() {
+            return self.compiled_status(compiled, data, end);
+        }
+
+        // Patterns not starting with the first byte are not tried, unless that
+        // byte may be a mismatch
+        if self.checked == 0 {
+            let table = &self.table;
+            self.candidates.clear();
+            if self.budget > 0 {
+                self.candidates.extend(0..self.patterns.len());
+            } else if let Some(ids) = data[0].to_usize().and_then(|value| table.get(value)) {
+                self.candidates.extend_from_slice(ids);
+            } else {
+                self.candidates = Self::starting_with(&self.first, data[0]);
+            }
+            for id in &self.candidates {
+                self.mismatches[*id].clear();
+                self.runs[*id] = None;
+            }
+        }
+
+        // Only bytes added since the last call are checked against simple patterns
+        let (patterns, simple, budget) = (self.patterns, &self.simple, self.budget);
+        let mismatches = &mut self.mismatches;
+        while self.checked < data.len() {
+            let (checked, byte) = (self.checked, data[self.checked]);
+            self.candidates
+                .retain(|id| match patterns[*id].get(checked) {
+                    Some(element) if simple[*id] && !element.matches(byte) => {
+                        if mismatches[*id].len() < budget {
+                            mismatches[*id].push(checked);
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    _ => true,
                 });
+            self.checked += 1;
+        }
+
+        let start = self.start - origin == self.window_start;
+
+        let mut partial = false;
+        let mut found = Vec::new();
+
+        let mut index = 0;
+        while let Some(&id) = self.candidates.get(index) {
+            let pattern = &patterns[id];
+
+            let outcome = if limited && pattern.ahead() {
+                Outcome::Fail
+            } else if !self.simple[id] {
+                // Bytes continuing an open run leave the outcome Partial
+                let waiting = match &mut self.runs[id] {
+                    Some((run, checked)) if !end => {
+                        let continued = Self::continues(run, &data[*checked..]);
+                        *checked = data.len();
+                        continued
+                    }
+                    _ => false,
+                };
+                if waiting {
+                    Outcome::Partial
+                } else {
+                    let (outcome, run) =
+                        pattern.match_open(joined, origin, start, end, self.budget);
+                    self.runs[id] = run.map(|run| (run, data.len()));
+                    outcome
+                }
+            } else if pattern.element_count() <= data.len() {
+                Outcome::Match {
+                    len: pattern.element_count(),
+                    mismatches: self.mismatches[id].clone(),
+                    captures: Vec::new(),
+                }
+            } else if end {
+                Outcome::Fail
+            } else {
+                Outcome::Partial
+            };
+
+            match outcome {
+                // Empty matches are not reported
+                Outcome::Fail | Outcome::Match { len: 0, .. } => {
+                    self.candidates.remove(index);
+                    continue;
+                }
+                Outcome::Partial => partial = true,
+                Outcome::Match {
+                    len,
+                    mismatches,
+                    captures,
+                } => found.push(Found {
+                    id,
+                    len,
+                    mismatches,
+                    captures,
+                }),
+            }
+
+            // Without overlapping the pattern added first wins, no matter which
+            // patterns come after it
+            if !self.overlapping && (partial || !found.is_empty()) {
+                break;
             }
-            None
+            index += 1;
+        }
+
+        if partial {
+            Status::Partial
+        } else if found.is_empty() {
+            Status::Fail(1)
         } else {
-            let byte = self.data.remove(0);
-            Some(PatternSearchType::NonMatch(byte))
+            Status::Match(found)
+        }
+    }
+
+    // Takes bytes into run, false if one of them ends it
+    fn continues(run: &mut OpenRun<'a, T>, bytes: &[T]) -> bool {
+        for byte in bytes {
+            if run.remaining <= 1 || !run.element.matches(*byte) {
+                return false;
+            }
+            run.remaining -= 1;
+        }
+        true
+    }
+
+    // Names the captured ranges of a match of pattern id
+    fn captures(&self, id: usize, ranges: Vec<Option<Range<usize>>>) -> Captures {
+        Captures::new(self.patterns[id].group_names(), ranges)
+    }
+
+    // Returns the buffered bytes of a possible match
+    fn pending(&self) -> &[T] {
+        &self.data[self.kept..]
+    }
+
+    // Moves start of a possible match forward by count bytes. They are kept in
+    // front of data for lookbehind assertions as far as needed, but not outside
+    // the window.
+    fn advance(&mut self, count: usize) {
+        self.start += count;
+        let kept = (self.kept + count)
+            .min(self.behind)
+            .min(self.start.saturating_sub(self.window_start));
+        self.data.drain(..self.kept + count - kept);
+        self.kept = kept;
+
+        self.checked = 0;
+    }
+
+    // Resolves buffered data as far as possible. A mismatch only drops the first buffered
+    // byte, the remaining bytes are retried as start of a new match.
+    fn resolve(&mut self, end: bool) {
+        while !self.pending().is_empty() {
+            match self.status(end) {
+                Status::Partial => break,
+                Status::Match(found) if self.overlapping => {
+                    for Found {
+                        id,
+                        len,
+                        mismatches,
+                        captures,
+                    } in found
+                    {
+                        self.results.push_back(PatternSearchType::Match {
+                            data: self.pending()[..len].to_vec(),
+                            index: self.start,
+                            id,
+                            mismatches,
+                            captures: self.captures(id, captures),
+                        });
+                        self.covered = self.covered.max(self.start + len);
+                    }
+                    self.advance(1);
+                }
+                Status::Match(mut found) => {
+                    let Found {
+                        id,
+                        len,
+                        mismatches,
+                        captures,
+                    } = found.remove(0);
+                    let captures = self.captures(id, captures);
+                    self.results.push_back(PatternSearchType::Match {
+                        data: self.pending()[..len].to_vec(),
+                        index: self.start,
+                        id,
+                        mismatches,
+                        captures,
+                    });
+                    self.advance(len);
+                }
+                Status::Fail(count) => {
+                    let count = count.min(self.pending().len());
+                    for offset in 0..count {
+                        if self.start + offset >= self.covered {
+                            let byte = self.pending()[offset];
+                            self.results.push_back(PatternSearchType::NonMatch(byte));
+                        }
+                    }
+                    self.advance(count);
+                }
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::pseudo_random;
+    use std::str::FromStr;
+
+    fn search(pattern: &str, input: &[u8]) -> Vec<PatternSearchType<u8>> {
+        search_with(pattern, input, false)
+    }
+
+    fn search_with(pattern: &str, input: &[u8], overlapping: bool) -> Vec<PatternSearchType<u8>> {
+        search_set(&[pattern], input, overlapping)
+    }
+
+    fn search_approx(pattern: &str, input: &[u8], budget: usize) -> Vec<PatternSearchType<u8>> {
+        let pattern = Pattern::<u8>::from_str(pattern).unwrap();
+        let mut searcher = Searcher::new(std::slice::from_ref(&pattern));
+        searcher.set_budget(budget);
+        collect(searcher, input)
+    }
+
+    fn search_window<R: ops::RangeBounds<usize>>(
+        pattern: &str,
+        input: &[u8],
+        range: R,
+    ) -> Vec<PatternSearchType<u8>> {
+        let pattern = Pattern::<u8>::from_str(pattern).unwrap();
+        let mut searcher = Searcher::new(std::slice::from_ref(&pattern));
+        searcher.set_window(range);
+        collect(searcher, input)
+    }
+
+    fn search_set(
+        patterns: &[&str],
+        input: &[u8],
+        overlapping: bool,
+    ) -> Vec<PatternSearchType<u8>> {
+        let patterns: Vec<Pattern<u8>> = patterns
+            .iter()
+            .map(|pattern| Pattern::from_str(pattern).unwrap())
+            .collect();
+        let mut searcher = Searcher::new(&patterns);
+        searcher.set_overlapping(overlapping);
+        collect(searcher, input)
+    }
+
+    fn collect(mut searcher: Searcher<u8>, input: &[u8]) -> Vec<PatternSearchType<u8>> {
+        let mut results = Vec::new();
+
+        for byte in input {
+            if let Some(result) = searcher.handle_next(*byte) {
+                results.push(result);
+            }
+            while let Some(result) = searcher.handle_existing_data() {
+                results.push(result);
+            }
+        }
+        while let Some(result) = searcher.handle_end() {
+            results.push(result);
+        }
+        results
+    }
+
+    #[test]
+    fn match_inside_partial_match() {
+        let results = search("0x41 0x41 0x42", &[0x41, 0x41, 0x41, 0x42]);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x41),
+                PatternSearchType::Match {
+                    data: vec![0x41, 0x41, 0x42],
+                    index: 1,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn retry_after_late_mismatch() {
+        let results = search(
+            "0x01 0x02 0x01 0x03",
+            &[0x01, 0x02, 0x01, 0x02, 0x01, 0x03, 0x01],
+        );
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x01),
+                PatternSearchType::NonMatch(0x02),
+                PatternSearchType::Match {
+                    data: vec![0x01, 0x02, 0x01, 0x03],
+                    index: 2,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::NonMatch(0x01),
+            ]
+        );
+    }
+
+    #[test]
+    fn non_overlapping() {
+        let results = search("0x41 0x41", &[0x41, 0x41, 0x41, 0x41, 0x41]);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::Match {
+                    data: vec![0x41, 0x41],
+                    index: 0,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::Match {
+                    data: vec![0x41, 0x41],
+                    index: 2,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::NonMatch(0x41),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_pattern() {
+        let results = search("", &[0x41, 0x42]);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x41),
+                PatternSearchType::NonMatch(0x42),
+            ]
+        );
+    }
+
+    #[test]
+    fn overlapping() {
+        let results = search_with("0x41 0x41", &[0x42, 0x41, 0x41, 0x41, 0x42], true);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x42),
+                PatternSearchType::Match {
+                    data: vec![0x41, 0x41],
+                    index: 1,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::Match {
+                    data: vec![0x41, 0x41],
+                    index: 2,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::NonMatch(0x42),
+            ]
+        );
+    }
+
+    #[test]
+    fn pattern_set() {
+        let results = search_set(
+            &["0x01 0x02 0x03", "0x02", "0x01 0x02"],
+            &[0x01, 0x02, 0x04, 0x01, 0x02, 0x03],
+            false,
+        );
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::Match {
+                    data: vec![0x01, 0x02],
+                    index: 0,
+                    id: 2,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::NonMatch(0x04),
+                PatternSearchType::Match {
+                    data: vec![0x01, 0x02, 0x03],
+                    index: 3,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn pattern_set_overlapping() {
+        let results = search_set(&["0x01 0x02", "0x02", "0x01"], &[0x01, 0x02], true);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::Match {
+                    data: vec![0x01, 0x02],
+                    index: 0,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::Match {
+                    data: vec![0x01],
+                    index: 0,
+                    id: 2,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::Match {
+                    data: vec![0x02],
+                    index: 1,
+                    id: 1,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn compiled_same_as_plain() {
+        let input = pseudo_random(1, 2000, 0x03);
+
+        for pattern in &["0x41 0x42 0x41", "0x4? 0x43 0x?? 0x40", "0x40 0x40", "0x42"] {
+            let pattern = Pattern::<u8>::from_str(pattern).unwrap();
+            let compiled = pattern.compile();
+            for overlapping in &[false, true] {
+                for (modulus, window) in &[(1, 0..usize::MAX), (3, 0..usize::MAX), (2, 501..1499)] {
+                    let mut plain = Searcher::from_ref((&pattern).into());
+                    let mut skipping = Searcher::from_ref((&compiled).into());
+                    for searcher in [&mut plain, &mut skipping] {
+                        searcher.set_overlapping(*overlapping);
+                        searcher.set_alignment(*modulus, 1);
+                        searcher.set_window(window.clone());
+                    }
+                    assert_eq!(collect(plain, &input), collect(skipping, &input));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_same_as_backtracking() {
+        let input = pseudo_random(3, 1000, 0x03);
+
+        let patterns: Vec<Pattern<u8>> = ["0x41 0x42 0x4? 0x40", "0x42 [1-2] 0x43", "0x43 !0x40"]
+            .iter()
+            .map(|pattern| Pattern::from_str(pattern).unwrap())
+            .collect();
+        for budget in 0..2 {
+            for overlapping in &[false, true] {
+                let mut incremental = Searcher::new(&patterns);
+                let mut backtracking = Searcher::new(&patterns);
+                backtracking.simple = vec![false; patterns.len()];
+                for searcher in [&mut incremental, &mut backtracking] {
+                    searcher.set_budget(budget);
+                    searcher.set_overlapping(*overlapping);
+                }
+                assert_eq!(collect(incremental, &input), collect(backtracking, &input));
+            }
+        }
+    }
+
+    #[test]
+    fn prefilter_same_as_unfiltered() {
+        let input = pseudo_random(5, 1000, 0x03);
+
+        let patterns: Vec<Pattern<u8>> = [
+            "0x41 0x42",
+            "(?<= 0x40 ) ( 0x42 [1-2] ) 0x43",
+            "0x43+ 0x40",
+            "0x40* 0x41",
+            "!0x40 0x41{2}",
+            "(?= 0x42 ) 0x4? 0x43",
+        ]
+        .iter()
+        .map(|pattern| Pattern::from_str(pattern).unwrap())
+        .collect();
+        for overlapping in &[false, true] {
+            let mut filtered = Searcher::new(&patterns);
+            let mut unfiltered = Searcher::new(&patterns);
+            unfiltered.first = vec![None; patterns.len()];
+            unfiltered.table = Searcher::table(&unfiltered.first);
+            filtered.set_overlapping(*overlapping);
+            unfiltered.set_overlapping(*overlapping);
+            assert_eq!(collect(filtered, &input), collect(unfiltered, &input));
+        }
+    }
+
+    #[test]
+    fn wide_values() {
+        // Values not fitting into a byte are not looked up in the table
+        let patterns: Vec<Pattern<u16>> = ["0x1234 0x00??", "0x??34 0x0001", "0x0034"]
+            .iter()
+            .map(|pattern| Pattern::from_str(pattern).unwrap())
+            .collect();
+        let mut searcher = Searcher::new(&patterns);
+        let mut results = Vec::new();
+        for value in &[0x1234, 0x0001, 0x0034, 0x5634, 0x0001] {
+            results.extend(searcher.handle_next(*value));
+            while let Some(result) = searcher.handle_existing_data() {
+                results.push(result);
+            }
+        }
+        while let Some(result) = searcher.handle_end() {
+            results.push(result);
+        }
+        let found: Vec<(usize, usize)> = results
+            .into_iter()
+            .filter_map(|result| match result {
+                PatternSearchType::Match { index, id, .. } => Some((index, id)),
+                PatternSearchType::NonMatch(_) => None,
+            })
+            .collect();
+        assert_eq!(found, [(0, 0), (2, 2), (3, 1)]);
+    }
+
+    #[test]
+    fn approx() {
+        let results = search_approx(
+            "0x01 0x02 0x03 0x04",
+            &[0x00, 0x01, 0x05, 0x03, 0x04, 0x01, 0x05, 0x06, 0x04],
+            1,
+        );
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x00),
+                PatternSearchType::Match {
+                    data: vec![0x01, 0x05, 0x03, 0x04],
+                    index: 1,
+                    id: 0,
+                    mismatches: vec![1],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::NonMatch(0x01),
+                PatternSearchType::NonMatch(0x05),
+                PatternSearchType::NonMatch(0x06),
+                PatternSearchType::NonMatch(0x04),
+            ]
+        );
+    }
+
+    #[test]
+    fn approx_prefers_leftmost() {
+        let results = search_approx("0x01 0x02", &[0x00, 0x02, 0x01, 0x02], 1);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::Match {
+                    data: vec![0x00, 0x02],
+                    index: 0,
+                    id: 0,
+                    mismatches: vec![0],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::Match {
+                    data: vec![0x01, 0x02],
+                    index: 2,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn alternatives() {
+        let results = search("0x01 (0x40|0x8?)", &[0x01, 0x41, 0x01, 0x40, 0x01, 0x8f]);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x01),
+                PatternSearchType::NonMatch(0x41),
+                PatternSearchType::Match {
+                    data: vec![0x01, 0x40],
+                    index: 2,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::Match {
+                    data: vec![0x01, 0x8f],
+                    index: 4,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn repeat() {
+        let results = search("0x00{2,}", &[0x00, 0x00, 0x00, 0x01, 0x00]);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::Match {
+                    data: vec![0x00, 0x00, 0x00],
+                    index: 0,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::NonMatch(0x01),
+                PatternSearchType::NonMatch(0x00),
+            ]
+        );
+
+        let results = search("0x00{2,}?", &[0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::Match {
+                    data: vec![0x00, 0x00],
+                    index: 0,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::NonMatch(0x00),
+                PatternSearchType::NonMatch(0x01),
+            ]
+        );
+    }
+
+    #[test]
+    fn captures() {
+        let results = search(
+            "0xe8 (?<addr> 0x?? 0x?? ) [0-1] ( 0xc3 )",
+            &[0x00, 0xe8, 0x12, 0x34, 0xc3],
+        );
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x00),
+                PatternSearchType::Match {
+                    data: vec![0xe8, 0x12, 0x34, 0xc3],
+                    index: 1,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::new(
+                        vec![Some("addr".to_string()), None],
+                        vec![Some(1..3), Some(3..4)]
+                    ),
+                },
+            ]
+        );
+        if let PatternSearchType::Match { data, captures, .. } = &results[1] {
+            assert_eq!(data[captures.name("addr").unwrap()], [0x12, 0x34]);
+            assert_eq!(data[captures.get(1).unwrap()], [0xc3]);
+        }
+    }
+
+    #[test]
+    fn aligned() {
+        let pattern = Pattern::<u8>::from_str("0x41 0x41").unwrap();
+        let mut searcher = Searcher::new(std::slice::from_ref(&pattern));
+        searcher.set_alignment(2, 1);
+        let results = collect(searcher, &[0x41, 0x41, 0x42, 0x41, 0x41]);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x41),
+                PatternSearchType::NonMatch(0x41),
+                PatternSearchType::NonMatch(0x42),
+                PatternSearchType::Match {
+                    data: vec![0x41, 0x41],
+                    index: 3,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn aligned_zero() {
+        // Modulus 0 means no alignment instead of a panic
+        let pattern = Pattern::<u8>::from_str("0x41").unwrap();
+        let mut searcher = Searcher::new(std::slice::from_ref(&pattern));
+        searcher.set_alignment(0, 3);
+        let results = collect(searcher, &[0x42, 0x41]);
+        assert!(matches!(
+            results[1],
+            PatternSearchType::Match { index: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn window() {
+        let input = [0x41, 0x41, 0x00, 0x41, 0x41, 0x41, 0x41];
+        for compiled in &[false, true] {
+            let pattern = Pattern::<u8>::from_str("0x41 0x41").unwrap();
+            let compiled_pattern = pattern.compile();
+            let mut searcher = if *compiled {
+                Searcher::from_ref(PatternRef::Compiled(&compiled_pattern))
+            } else {
+                Searcher::new(std::slice::from_ref(&pattern))
+            };
+            searcher.set_window(1..6);
+            let results = collect(searcher, &input);
+            assert_eq!(
+                results,
+                vec![
+                    PatternSearchType::NonMatch(0x41),
+                    PatternSearchType::NonMatch(0x41),
+                    PatternSearchType::NonMatch(0x00),
+                    PatternSearchType::Match {
+                        data: vec![0x41, 0x41],
+                        index: 3,
+                        id: 0,
+                        mismatches: vec![],
+                        captures: Captures::default(),
+                    },
+                    PatternSearchType::NonMatch(0x41),
+                    PatternSearchType::NonMatch(0x41),
+                ]
+            );
+        }
+
+        // Greedy repeats stop at the end of the window
+        let results = search_window("0x41+", &input, 3..=4);
+        assert_eq!(results.len(), 6);
+        assert_eq!(
+            results[3],
+            PatternSearchType::Match {
+                data: vec![0x41, 0x41],
+                index: 3,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn window_bounds() {
+        let input = [0x41, 0x41];
+        let results = search_window("0x41", &input, ..=usize::MAX);
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[1], PatternSearchType::Match { .. }));
+
+        let results = search_window(
+            "0x41",
+            &input,
+            (ops::Bound::Excluded(usize::MAX), ops::Bound::Unbounded),
+        );
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x41),
+                PatternSearchType::NonMatch(0x41)
+            ]
+        );
+    }
+
+    #[test]
+    fn anchors() {
+        let results = search("^ 0x41", &[0x41, 0x41]);
+        assert_eq!(results.len(), 2);
+        assert!(matches!(
+            results[0],
+            PatternSearchType::Match { index: 0, .. }
+        ));
+
+        // End is only known when the input is exhausted
+        let results = search("0x41 [0-1] $", &[0x41, 0x42, 0x41, 0x41, 0x00]);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x41),
+                PatternSearchType::NonMatch(0x42),
+                PatternSearchType::NonMatch(0x41),
+                PatternSearchType::Match {
+                    data: vec![0x41, 0x00],
+                    index: 3,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+            ]
+        );
+
+        let results = search_window("^ 0x41 0x41 $", &[0x41, 0x41, 0x41, 0x41], 1..3);
+        assert!(matches!(
+            results[1],
+            PatternSearchType::Match { index: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn assertions() {
+        // Bytes seen by lookahead are not part of the match
+        let results = search("0x41 (?= 0x42 0x42 )", &[0x41, 0x41, 0x42, 0x42]);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x41),
+                PatternSearchType::Match {
+                    data: vec![0x41],
+                    index: 1,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::NonMatch(0x42),
+                PatternSearchType::NonMatch(0x42),
+            ]
+        );
+
+        // Lookbehind sees bytes already handed out, but not those before the window
+        let input = [0x42, 0x41, 0x41, 0x42, 0x41];
+        let results = search("(?<= 0x42 ) 0x41", &input);
+        assert_eq!(results.len(), 5);
+        assert!(matches!(
+            results[1],
+            PatternSearchType::Match { index: 1, .. }
+        ));
+        assert!(matches!(
+            results[4],
+            PatternSearchType::Match { index: 4, .. }
+        ));
+
+        let results = search_window("(?<! 0x42 ) 0x41", &input, 1..);
+        assert!(matches!(
+            results[1],
+            PatternSearchType::Match { index: 1, .. }
+        ));
+        assert_eq!(results[4], PatternSearchType::NonMatch(0x41));
+    }
+
+    #[test]
+    fn gap() {
+        let results = search(
+            "0x01 [1-3] 0x02",
+            &[0x01, 0x01, 0x01, 0x03, 0x03, 0x02, 0x01, 0x02, 0x02],
+        );
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x01),
+                PatternSearchType::Match {
+                    data: vec![0x01, 0x01, 0x03, 0x03, 0x02],
+                    index: 1,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+                PatternSearchType::Match {
+                    data: vec![0x01, 0x02, 0x02],
+                    index: 6,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn gap_at_end() {
+        let results = search("0x01 [2-4]", &[0x00, 0x01, 0x02, 0x03]);
+        assert_eq!(
+            results,
+            vec![
+                PatternSearchType::NonMatch(0x00),
+                PatternSearchType::Match {
+                    data: vec![0x01, 0x02, 0x03],
+                    index: 1,
+                    id: 0,
+                    mismatches: vec![],
+                    captures: Captures::default(),
+                },
+            ]
+        );
+    }
+}
@@ -1,9 +1,10 @@
-use crate::{OptFifo, PatternSearchType};
+use crate::{OptFifo, PatternSearchType, Position};
 
 pub struct Taker<T> {
     found: usize,
     count: usize,
     data: OptFifo<T>,
+    position: Position,
 }
 
 impl<T> Taker<T> {
@@ -12,6 +13,7 @@ impl<T> Taker<T> {
             found: 0,
             count,
             data: OptFifo::new(),
+            position: Position::new(),
         }
     }
 
@@ -19,19 +21,40 @@ impl<T> Taker<T> {
         self.data.get()
     }
 
-    pub fn handle_next(&mut self, search_type: PatternSearchType<T>) -> PatternSearchType<T> {
+    // Returns None if nothing is left to return, which happens for a match
+    // completely overlapped by earlier matches (see overlapping search)
+    pub fn handle_next(
+        &mut self,
+        search_type: PatternSearchType<T>,
+    ) -> Option<PatternSearchType<T>> {
         match search_type {
-            PatternSearchType::Match { mut data, index } => {
+            PatternSearchType::Match {
+                data,
+                index,
+                id,
+                mismatches,
+                captures,
+            } => {
                 self.found += 1;
                 if self.found > self.count {
-                    let byte = data.remove(0);
+                    let data = self.position.unseen(data, index);
                     self.data.set(data);
-                    return PatternSearchType::NonMatch(byte);
+                    self.data.get().map(PatternSearchType::NonMatch)
                 } else {
-                    return PatternSearchType::Match { data, index };
+                    self.position.advance(index, data.len());
+                    Some(PatternSearchType::Match {
+                        data,
+                        index,
+                        id,
+                        mismatches,
+                        captures,
+                    })
                 }
             }
-            PatternSearchType::NonMatch(_) => return search_type,
+            PatternSearchType::NonMatch(_) => {
+                self.position.non_match();
+                Some(search_type)
+            }
         }
     }
 }
@@ -0,0 +1,13 @@
+// Helpers shared by the tests of several modules
+
+// Simple pseudo random input of len bytes 0x40 | (x & mask), so only few
+// different values occur and partial matches are frequent
+pub fn pseudo_random(seed: u32, len: usize, mask: u8) -> Vec<u8> {
+    let mut value = seed;
+    (0..len)
+        .map(|_| {
+            value = value.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            0x40 | ((value >> 16) as u8 & mask)
+        })
+        .collect()
+}
@@ -0,0 +1,244 @@
+use crate::{Element, MaskedByte};
+use std::fmt;
+use std::ops;
+use std::str;
+
+/// Typed is an integer of 8 to 64 bits with given signedness and endianness, like
+/// "u32le:0x12??56??" or "i16be:-5". It matches the sequence of bytes the integer
+/// is stored as. Undefined bits stay undefined in the bytes.
+#[derive(PartialEq, Clone)]
+pub struct Typed<T> {
+    bits: u32,
+    signed: bool,
+    big_endian: bool,
+    value: u64,
+    mask: u64,
+
+    // One fully expanded MaskedByte element per byte in memory order
+    elements: Vec<Element<T>>,
+}
+
+impl<T> fmt::Debug for Typed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Typed {{ type: {}, value: {:#x}, mask: {:#x} }}",
+            self.type_name(),
+            self.value,
+            self.mask
+        )
+    }
+}
+
+impl<T> fmt::Display for Typed<T> {
+    /// Fully defined signed values are shown as decimal number, others in
+    /// hexadecimal or, if a nibble is partly undefined, binary.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.type_name())?;
+
+        let full = width_mask(self.bits);
+        if self.signed && self.mask == full {
+            let shift = 64 - self.bits;
+            return write!(f, "{}", ((self.value << shift) as i64) >> shift);
+        }
+
+        let (prefix, digit_bits) = if (0..self.bits / 4)
+            .map(|digit| (self.mask >> (digit * 4)) & 0xf)
+            .all(|nibble| nibble == 0 || nibble == 0xf)
+        {
+            ("0x", 4)
+        } else {
+            ("0b", 1)
+        };
+
+        write!(f, "{}", prefix)?;
+        let digit_mask = (1 << digit_bits) - 1;
+        for digit in (0..self.bits / digit_bits).rev() {
+            let shift = digit * digit_bits;
+            if (self.mask >> shift) & digit_mask == 0 {
+                write!(f, "?")?;
+            } else {
+                write!(f, "{:x}", (self.value >> shift) & digit_mask)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Mask with the lowest bits set
+fn width_mask(bits: u32) -> u64 {
+    u64::MAX >> (64 - bits)
+}
+
+impl<T> Typed<T>
+where
+    T: From<u8>
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    fn new(bits: u32, signed: bool, big_endian: bool, value: u64, mask: u64) -> Self {
+        let count = bits / 8;
+        let elements = (0..count)
+            .map(|index| {
+                let shift = if big_endian { count - 1 - index } else { index } * 8;
+                Element::Byte(MaskedByte::new(
+                    From::from((value >> shift) as u8),
+                    From::from((mask >> shift) as u8),
+                ))
+            })
+            .collect();
+
+        Self {
+            bits,
+            signed,
+            big_endian,
+            value,
+            mask,
+            elements,
+        }
+    }
+
+    /// Returns the bytes in memory order
+    pub fn elements(&self) -> &[Element<T>] {
+        &self.elements
+    }
+}
+
+impl<T> Typed<T> {
+    fn type_name(&self) -> String {
+        format!(
+            "{}{}{}",
+            if self.signed { 'i' } else { 'u' },
+            self.bits,
+            if self.big_endian { "be" } else { "le" }
+        )
+    }
+}
+
+impl<T> str::FromStr for Typed<T>
+where
+    T: From<u8>
+        + ops::ShlAssign<u32>
+        + ops::BitOrAssign
+        + ops::BitAndAssign
+        + PartialEq
+        + num::PrimInt
+        + num::Unsigned
+        + Default
+        + num::PrimInt<FromStrRadixErr = std::num::ParseIntError>,
+{
+    type Err = crate::Error;
+
+    /// Creates Typed from "type:value". Type is "u" or "i" followed by 8, 16, 32 or
+    /// 64 bits and "le" or "be". Value is a decimal number or a MaskedByte like
+    /// string with base prefix. Only patterns of bytes support typed values.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let invalid = || crate::Error::InvalidTyped {
+            string: string.to_string(),
+        };
+
+        if T::zero().count_zeros() != 8 {
+            return Err(invalid());
+        }
+
+        let (name, number) = string.split_once(':').ok_or_else(invalid)?;
+        let signed = match name.get(..1) {
+            Some("u") => false,
+            Some("i") => true,
+            _ => return Err(invalid()),
+        };
+        let rest = &name[1..];
+        let (bits, big_endian) = match (rest.strip_suffix("le"), rest.strip_suffix("be")) {
+            (Some(bits), _) => (bits, false),
+            (_, Some(bits)) => (bits, true),
+            _ => return Err(invalid()),
+        };
+        let bits: u32 = match bits.parse() {
+            Ok(bits @ (8 | 16 | 32 | 64)) => bits,
+            _ => return Err(invalid()),
+        };
+        let full = width_mask(bits);
+
+        if matches!(number.get(..2), Some("0x" | "0b" | "0o")) {
+            let masked = MaskedByte::<u64>::from_str(number)?;
+            let (value, mask) = (masked.value(), masked.mask());
+            if value & !full != 0 || mask | full != u64::MAX {
+                return Err(invalid());
+            }
+            return Ok(Self::new(bits, signed, big_endian, value, mask & full));
+        }
+
+        let number: i128 = number.parse().map_err(|_| invalid())?;
+        let (min, max) = if signed {
+            (-(1_i128 << (bits - 1)), (1_i128 << (bits - 1)) - 1)
+        } else {
+            (0, (1_i128 << bits) - 1)
+        };
+        if number < min || number > max {
+            return Err(invalid());
+        }
+        Ok(Self::new(
+            bits,
+            signed,
+            big_endian,
+            number as u64 & full,
+            full,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn bytes(typed: &Typed<u8>) -> Vec<String> {
+        typed
+            .elements()
+            .iter()
+            .map(|element| element.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn expand() {
+        let t = Typed::<u8>::from_str("u16le:0x1234").unwrap();
+        assert_eq!(bytes(&t), ["00110100", "00010010"]);
+
+        let t = Typed::<u8>::from_str("u32be:0x12??56??").unwrap();
+        assert_eq!(bytes(&t), ["00010010", "????????", "01010110", "????????"]);
+
+        let t = Typed::<u8>::from_str("i32le:-5").unwrap();
+        assert_eq!(bytes(&t), ["11111011", "11111111", "11111111", "11111111"]);
+    }
+
+    #[test]
+    fn string_conversion() {
+        for (string, display) in &[
+            ("u16le:0x1234", "u16le:0x1234"),
+            ("u16le:4660", "u16le:0x1234"),
+            ("u32be:0x12??56??", "u32be:0x12??56??"),
+            ("i32le:-5", "i32le:-5"),
+            ("i8le:0x7f", "i8le:127"),
+            ("u8be:0b1??0????", "u8be:0b1??0????"),
+            ("u64le:0xffffffffffffffff", "u64le:0xffffffffffffffff"),
+        ] {
+            assert_eq!(Typed::<u8>::from_str(string).unwrap().to_string(), *display);
+        }
+
+        assert!(Typed::<u8>::from_str("u16le:0x12345").is_err());
+        assert!(Typed::<u8>::from_str("u16le:65536").is_err());
+        assert!(Typed::<u8>::from_str("u16le:-1").is_err());
+        assert!(Typed::<u8>::from_str("i8le:-129").is_err());
+        assert!(Typed::<u8>::from_str("u8le:0x???").is_err());
+        assert!(Typed::<u8>::from_str("u24le:0").is_err());
+        assert!(Typed::<u8>::from_str("u16:0").is_err());
+        assert!(Typed::<u16>::from_str("u16le:0").is_err());
+    }
+}
@@ -0,0 +1,328 @@
+use crate::parser::Located;
+use crate::{Alternatives, Element, MaskedByte};
+use std::ops::Range;
+use std::str::FromStr;
+
+// Elements and the char index range each was read from
+type Sequence = (Vec<Element<u8>>, Vec<Range<usize>>);
+
+// Reads a YARA hex string like "{ 4D 5A ?? [2-4] (6A | 6B) ~00 }" char by char
+struct Reader {
+    chars: Vec<char>,
+    index: usize,
+}
+
+impl Reader {
+    // Error for the chars from start to the current index, at least one
+    fn invalid(&self, start: usize) -> Located {
+        let end = self.index.max(start + 1).min(self.chars.len());
+        let string = self.chars[start.min(end)..end].iter().collect();
+        (start, crate::Error::InvalidYara { string })
+    }
+
+    fn unsupported(&self, start: usize) -> Located {
+        let string = self.chars[start..self.index].iter().collect();
+        (start, crate::Error::YaraUnsupported { string })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    // Skips whitespace and comments, fails at the opener of an unterminated
+    // comment
+    fn skip(&mut self) -> Result<(), Located> {
+        loop {
+            match (self.peek(), self.chars.get(self.index + 1)) {
+                (Some(c), _) if c.is_whitespace() => self.index += 1,
+                (Some('/'), Some('/')) => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.index += 1;
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    let start = self.index;
+                    self.index += 2;
+                    while self.index < self.chars.len()
+                        && !(self.chars[self.index - 1] == '*' && self.chars[self.index] == '/')
+                    {
+                        self.index += 1;
+                    }
+                    if self.index >= self.chars.len() {
+                        self.index = start + 2;
+                        return Err(self.invalid(start));
+                    }
+                    self.index += 1;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    // Reads a byte like "4D", "??" or "?D"
+    fn byte(&mut self) -> Result<MaskedByte<u8>, Located> {
+        let start = self.index;
+        let digits: String = self.chars[start..]
+            .iter()
+            .take(2)
+            .take_while(|c| !c.is_whitespace())
+            .collect();
+        self.index += digits.chars().count();
+        if digits.chars().count() != 2 || !digits.chars().all(|c| c == '?' || c.is_ascii_hexdigit())
+        {
+            return Err(self.invalid(start));
+        }
+        MaskedByte::from_str(&format!("0x{}", digits)).map_err(|_| self.invalid(start))
+    }
+
+    // Reads a jump like "[4]", "[2-4]", "[2-]" or "[-]"
+    fn jump(&mut self) -> Result<Element<u8>, Located> {
+        let start = self.index;
+        let end = self.chars[start..]
+            .iter()
+            .position(|c| *c == ']')
+            .ok_or_else(|| self.invalid(start))?;
+        self.index += end + 1;
+
+        let inner: String = self.chars[start + 1..start + end]
+            .iter()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        // A jump can not be longer than a match
+        let number = |string: &str| {
+            string
+                .parse::<usize>()
+                .ok()
+                .filter(|count| *count <= crate::MAX_MATCH_LEN)
+                .ok_or_else(|| self.invalid(start))
+        };
+        let (min, max) = match inner.split_once('-') {
+            None => {
+                let count = number(&inner)?;
+                (count, Some(count))
+            }
+            Some((min, max)) => (
+                if min.is_empty() { 0 } else { number(min)? },
+                if max.is_empty() {
+                    None
+                } else {
+                    Some(number(max)?)
+                },
+            ),
+        };
+
+        match max {
+            Some(max) if max < min => Err(self.invalid(start)),
+            Some(max) => Ok(Element::Gap { min, max }),
+            None => Ok(Element::Repeat {
+                element: Box::new(Element::Byte(MaskedByte::default())),
+                min,
+                max: None,
+                greedy: false,
+            }),
+        }
+    }
+
+    // Reads alternatives like "(6A | 6B)". Only alternatives of single bytes can be
+    // represented.
+    fn alternatives(&mut self) -> Result<Element<u8>, Located> {
+        let start = self.index;
+        self.index += 1;
+
+        let mut options = Vec::new();
+        loop {
+            options.push(self.sequence(true)?.0);
+            match self.peek() {
+                Some('|') => self.index += 1,
+                Some(')') => {
+                    self.index += 1;
+                    break;
+                }
+                _ => return Err(self.invalid(start)),
+            }
+        }
+
+        let mut values = Vec::new();
+        for option in options {
+            match option.as_slice() {
+                [Element::Byte(value)] => values.push(*value),
+                [Element::Alternatives(alternatives)] => {
+                    values.extend_from_slice(alternatives.values())
+                }
+                [] => return Err(self.invalid(start)),
+                _ => return Err(self.unsupported(start)),
+            }
+        }
+        Ok(Element::Alternatives(Alternatives::new(values)))
+    }
+
+    // Reads elements up to a closing brace, parenthesis or, inside of
+    // alternatives, "|". Returns the char index range of each element too.
+    fn sequence(&mut self, nested: bool) -> Result<Sequence, Located> {
+        let mut elements = Vec::new();
+        let mut spans = Vec::new();
+        loop {
+            self.skip()?;
+            let start = self.index;
+            let element = match self.peek() {
+                None | Some('}') => break,
+                Some(')' | '|') if nested => break,
+                Some('[') => self.jump()?,
+                Some('(') => self.alternatives()?,
+                Some('~') => {
+                    self.index += 1;
+                    let value = self.byte().map_err(|_| self.invalid(start))?;
+                    if value.matches_any() {
+                        return Err(self.invalid(start));
+                    }
+                    Element::Not(Box::new(Element::Byte(value)))
+                }
+                Some(_) => Element::Byte(self.byte()?),
+            };
+            elements.push(element);
+            spans.push(start..self.index);
+        }
+        Ok((elements, spans))
+    }
+}
+
+// Parses a YARA hex string, the braces are optional. Jumps become gaps or, if
+// unlimited, lazy repeats of any byte. Errors come with the char index they
+// occurred at.
+pub fn parse(string: &str) -> Result<Vec<Element<u8>>, Located> {
+    let mut reader = Reader {
+        chars: string.chars().collect(),
+        index: 0,
+    };
+
+    reader.skip()?;
+    let open = reader.index;
+    let braced = reader.peek() == Some('{');
+    if braced {
+        reader.index += 1;
+    }
+    let (elements, spans) = reader.sequence(false)?;
+
+    let end = reader.index;
+    if braced {
+        // An unterminated hex string is reported at its opening brace
+        match reader.peek() {
+            Some('}') => {}
+            None => {
+                reader.index = open + 1;
+                return Err(reader.invalid(open));
+            }
+            Some(_) => return Err(reader.invalid(end)),
+        }
+        reader.index += 1;
+        reader.skip()?;
+    }
+    if reader.peek().is_some() {
+        return Err(reader.invalid(reader.index));
+    }
+
+    // Jumps are not allowed at the bounds, an empty hex string is reported at its
+    // end
+    let jump = |index: usize| {
+        matches!(
+            elements[index],
+            Element::Gap { .. } | Element::Repeat { .. }
+        )
+    };
+    let bound = match elements.len() {
+        0 => {
+            reader.index = end;
+            return Err(reader.invalid(end));
+        }
+        _ if jump(0) => spans[0].clone(),
+        len if jump(len - 1) => spans[len - 1].clone(),
+        _ => return Ok(elements),
+    };
+    reader.index = bound.end;
+    Err(reader.invalid(bound.start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex_string() {
+        let elements = parse("{ 4D 5A ?? [2-4] (6A | 6B) ~00 [3-] ?F }").unwrap();
+        assert_eq!(
+            elements,
+            vec![
+                Element::Byte(MaskedByte::new(0x4d, 0xff)),
+                Element::Byte(MaskedByte::new(0x5a, 0xff)),
+                Element::Byte(MaskedByte::new(0x00, 0x00)),
+                Element::Gap { min: 2, max: 4 },
+                Element::Alternatives(Alternatives::new(vec![
+                    MaskedByte::new(0x6a, 0xff),
+                    MaskedByte::new(0x6b, 0xff),
+                ])),
+                Element::Not(Box::new(Element::Byte(MaskedByte::new(0x00, 0xff)))),
+                Element::Repeat {
+                    element: Box::new(Element::Byte(MaskedByte::default())),
+                    min: 3,
+                    max: None,
+                    greedy: false,
+                },
+                Element::Byte(MaskedByte::new(0x0f, 0x0f)),
+            ]
+        );
+
+        assert_eq!(
+            parse("4D /* MZ */ 5A // header\n [2] ((01 | 02) | 03) 00")
+                .unwrap()
+                .len(),
+            5
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |string| parse(string).unwrap_err();
+
+        assert!(matches!(
+            error("{ 4D 5 }"),
+            (5, crate::Error::InvalidYara { .. })
+        ));
+        assert!(matches!(
+            error("{ 4D [4-2] 00 }"),
+            (5, crate::Error::InvalidYara { .. })
+        ));
+        assert!(matches!(
+            error("{ 4D [0-18446744073709551615] 00 }"),
+            (5, crate::Error::InvalidYara { .. })
+        ));
+        assert!(matches!(
+            error("{ 4D (00 | 01 00 }"),
+            (5, crate::Error::InvalidYara { .. })
+        ));
+        assert!(matches!(
+            error("{ 4D ~?? }"),
+            (5, crate::Error::InvalidYara { .. })
+        ));
+        // Errors point at the offending token
+        let invalid = |string| match parse(string).unwrap_err() {
+            (index, crate::Error::InvalidYara { string }) => (index, string),
+            other => panic!("Unexpected {:?}", other),
+        };
+        assert_eq!(invalid("{ 4D 00"), (0, "{".to_string()));
+        assert_eq!(invalid("{ [2] 4D }"), (2, "[2]".to_string()));
+        assert_eq!(invalid("{ 4D [2-] }"), (5, "[2-]".to_string()));
+        assert_eq!(invalid("{ }"), (2, "}".to_string()));
+        assert_eq!(invalid("{ 4D /* x"), (5, "/*".to_string()));
+        assert_eq!(invalid("~"), (0, "~".to_string()));
+        assert_eq!(invalid("{ 4D ~0 }"), (5, "~0".to_string()));
+
+        match error("{ 4D (00 01 | 02) }") {
+            (5, crate::Error::YaraUnsupported { string }) => assert_eq!(string, "(00 01 | 02)"),
+            other => panic!("Unexpected {:?}", other),
+        }
+        assert!(matches!(
+            error("{ 4D (00 | ~01) }"),
+            (5, crate::Error::YaraUnsupported { .. })
+        ));
+    }
+}
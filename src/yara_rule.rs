@@ -0,0 +1,410 @@
+use crate::code;
+use crate::{Element, Pattern, PatternFormatter};
+use std::fmt::Write;
+use std::slice;
+
+// Most alternatives a single byte is split into before it is widened
+const SPLIT_LIMIT: usize = 16;
+
+/// Text of an exported YARA rule. Warnings name the places where the rule matches
+/// more than the Patterns do.
+#[derive(PartialEq, Debug)]
+pub struct YaraExport {
+    pub rule: String,
+    pub warnings: Vec<String>,
+}
+
+/// YaraRule writes one or more Patterns as YARA rule, like
+/// YaraRule::new("mz_header").meta("author", "me").pattern(&pattern).export().
+/// The rule matches if any of the Patterns does.
+pub struct YaraRule<'a> {
+    name: String,
+    meta: Vec<(String, String)>,
+    patterns: Vec<&'a Pattern<u8>>,
+}
+
+// Writes the hex string of a single Pattern
+struct Writer<'w> {
+    id: usize,
+    tokens: Vec<String>,
+    warnings: &'w mut Vec<String>,
+
+    // Index of the top level element being written and of the one each token
+    // belongs to
+    position: usize,
+    positions: Vec<usize>,
+}
+
+// Writes value as hex token, undefined nibbles of mask become "?"
+fn nibble_token(value: u8, mask: u8) -> Option<String> {
+    let digit = |shift: u32| match (mask >> shift) & 0xf {
+        0 => Some('?'),
+        0xf => std::char::from_digit(u32::from((value >> shift) & 0xf), 16),
+        _ => None,
+    };
+    Some(format!("{}{}", digit(4)?, digit(0)?).to_uppercase())
+}
+
+// True if name is a YARA identifier, those are at most 128 chars long
+fn identifier(name: &str) -> bool {
+    code::is_identifier(name) && name.len() <= 128
+}
+
+// Escapes value for a YARA string literal
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_ascii_control() => write!(escaped, "\\x{:02x}", c as u8).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Error for element at index of the Pattern
+fn unsupported(element: &Element<u8>, index: usize) -> crate::Error {
+    crate::Error::FormatUnsupported {
+        element: PatternFormatter::new().element(element),
+        index,
+    }
+}
+
+impl<'w> Writer<'w> {
+    fn warn(&mut self, message: String) {
+        self.warnings.push(format!("$p{}: {}", self.id, message));
+    }
+
+    fn push(&mut self, token: String) {
+        self.tokens.push(token);
+        self.positions.push(self.position);
+    }
+
+    // Writes a single byte element as token, as alternatives of tokens or, if
+    // those get too many, widened to the nibbles all values share
+    fn single(&mut self, element: &Element<u8>) -> crate::Result<String> {
+        match element {
+            Element::Byte(masked_byte) => {
+                if let Some(token) = nibble_token(masked_byte.value(), masked_byte.mask()) {
+                    return Ok(token);
+                }
+            }
+            Element::Not(negated) => {
+                if let Element::Byte(masked_byte) = &**negated {
+                    if let Some(token) = nibble_token(masked_byte.value(), masked_byte.mask()) {
+                        if !masked_byte.matches_any() {
+                            return Ok(format!("~{}", token));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let values: Vec<u8> = (0..=u8::MAX)
+            .filter(|value| element.matches(*value))
+            .collect();
+        if values.is_empty() {
+            return Err(unsupported(element, self.position));
+        }
+
+        // Values sharing the high nibble are written as one token if all low
+        // nibbles are present
+        let mut tokens = Vec::new();
+        for high in 0..16_u8 {
+            let low: Vec<u8> = values
+                .iter()
+                .filter(|value| *value >> 4 == high)
+                .copied()
+                .collect();
+            if low.len() == 16 {
+                tokens.push(nibble_token(high << 4, 0xf0).unwrap());
+            } else {
+                tokens.extend(low.iter().map(|value| nibble_token(*value, 0xff).unwrap()));
+            }
+        }
+
+        if tokens.len() == 16 && tokens.iter().all(|token| token.ends_with('?')) {
+            return Ok("??".to_string());
+        }
+        if tokens.len() == 1 {
+            return Ok(tokens.remove(0));
+        }
+        if tokens.len() <= SPLIT_LIMIT {
+            return Ok(format!("({})", tokens.join(" | ")));
+        }
+
+        let first = values[0];
+        let mask = if values.iter().all(|value| value >> 4 == first >> 4) {
+            0xf0
+        } else {
+            0x00
+        };
+        let token = nibble_token(first, mask).unwrap();
+        let message = format!(
+            "{} widened to {}",
+            PatternFormatter::new().element(element),
+            token
+        );
+        self.warn(message);
+        Ok(token)
+    }
+
+    fn jump(min: usize, max: Option<usize>) -> String {
+        match max {
+            Some(max) if max == min => format!("[{}]", min),
+            Some(max) => format!("[{}-{}]", min, max),
+            None => format!("[{}-]", min),
+        }
+    }
+
+    fn elements(&mut self, elements: &[Element<u8>]) -> crate::Result<()> {
+        for element in elements {
+            match element {
+                Element::Byte(_)
+                | Element::Alternatives(_)
+                | Element::Range { .. }
+                | Element::Not(_) => {
+                    let token = self.single(element)?;
+                    self.push(token);
+                }
+                Element::Gap { min, max } => self.push(Self::jump(*min, Some(*max))),
+                Element::Repeat {
+                    element: repeated,
+                    min,
+                    max,
+                    ..
+                } => {
+                    if repeated.matches_any() {
+                        self.push(Self::jump(*min, *max));
+                        continue;
+                    }
+                    let token = self.single(repeated)?;
+                    for _ in 0..*min {
+                        self.push(token.clone());
+                    }
+                    if *max != Some(*min) {
+                        let element = PatternFormatter::new().element(element);
+                        self.warn(format!("{} widened to a jump", element));
+                        self.push(Self::jump(0, max.map(|max| max - min)));
+                    }
+                }
+                Element::Typed(_) | Element::Literal(_) => {
+                    self.elements(element.sequence().unwrap_or_default())?
+                }
+                Element::Group { elements, .. } => self.elements(elements)?,
+                Element::Start | Element::End | Element::Assertion { .. } => self.warn(format!(
+                    "{} dropped",
+                    PatternFormatter::new().element(element)
+                )),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> YaraRule<'a> {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            meta: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Adds a meta entry with a string value. The key needs to be an identifier,
+    /// export fails otherwise.
+    pub fn meta(mut self, key: &str, value: &str) -> Self {
+        self.meta.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Adds a Pattern, it becomes string $p0, $p1 and so on
+    pub fn pattern(mut self, pattern: &'a Pattern<u8>) -> Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    // Writes hex string and condition of Pattern id
+    fn string(&self, id: usize, warnings: &mut Vec<String>) -> crate::Result<(String, String)> {
+        let elements = self.patterns[id].elements();
+        let mut condition = format!("$p{}", id);
+
+        // Anchors at the bounds become a position in the condition
+        let mut inner = elements;
+        let mut offset = 0;
+        if let [Element::Start, rest @ ..] = inner {
+            write!(condition, " at 0").unwrap();
+            inner = rest;
+            offset = 1;
+        } else if let [rest @ .., Element::End] = inner {
+            let pattern = &self.patterns[id];
+            if Some(pattern.min_len()) == pattern.max_len() {
+                write!(condition, " at filesize - {}", pattern.min_len()).unwrap();
+                inner = rest;
+            }
+        }
+
+        let mut writer = Writer {
+            id,
+            tokens: Vec::new(),
+            warnings,
+            position: 0,
+            positions: Vec::new(),
+        };
+        for (index, element) in inner.iter().enumerate() {
+            writer.position = offset + index;
+            writer.elements(slice::from_ref(element))?;
+        }
+
+        // YARA does not allow jumps at the bounds of a hex string, the error names
+        // the element the jump at the bound was written for. An empty hex string is
+        // reported as the whole Pattern.
+        let jump = |token: &String| token.starts_with('[');
+        let position = match (writer.tokens.first(), writer.tokens.last()) {
+            (Some(first), Some(last)) if !jump(first) && !jump(last) => {
+                return Ok((format!("{{ {} }}", writer.tokens.join(" ")), condition))
+            }
+            (Some(first), _) if jump(first) => writer.positions[0],
+            (Some(_), _) => writer.positions[writer.positions.len() - 1],
+            (None, _) => {
+                return Err(crate::Error::FormatUnsupported {
+                    element: PatternFormatter::new().format(self.patterns[id]),
+                    index: 0,
+                })
+            }
+        };
+        Err(unsupported(&elements[position], position))
+    }
+
+    /// Writes the rule. Partly defined bytes are split into alternatives or, if
+    /// those get too many, widened to whole nibbles. Variable repeats of other
+    /// than any byte are widened to jumps, anchors not at the bounds and
+    /// assertions are dropped. Each widening adds a warning.
+    pub fn export(&self) -> crate::Result<YaraExport> {
+        if !identifier(&self.name) || self.patterns.is_empty() {
+            return Err(crate::Error::InvalidYara {
+                string: self.name.clone(),
+            });
+        }
+        if let Some((key, _)) = self.meta.iter().find(|(key, _)| !identifier(key)) {
+            return Err(crate::Error::InvalidIdentifier {
+                string: key.clone(),
+            });
+        }
+
+        let mut warnings = Vec::new();
+        let mut rule = format!("rule {}\n{{\n", self.name);
+
+        if !self.meta.is_empty() {
+            rule.push_str("    meta:\n");
+            for (key, value) in &self.meta {
+                writeln!(rule, "        {} = \"{}\"", key, escape(value)).unwrap();
+            }
+            rule.push('\n');
+        }
+
+        let mut conditions = Vec::new();
+        rule.push_str("    strings:\n");
+        for id in 0..self.patterns.len() {
+            let (string, condition) = self.string(id, &mut warnings)?;
+            writeln!(rule, "        $p{} = {}", id, string).unwrap();
+            conditions.push(condition);
+        }
+
+        let condition = if conditions.iter().all(|condition| !condition.contains(' ')) {
+            "any of them".to_string()
+        } else {
+            conditions.join(" or ")
+        };
+        write!(rule, "\n    condition:\n        {}\n}}\n", condition).unwrap();
+
+        Ok(YaraExport { rule, warnings })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn hex_string(pattern: &str) -> (String, Vec<String>) {
+        let pattern = Pattern::<u8>::from_str(pattern).unwrap();
+        let rule = YaraRule::new("test");
+        let mut warnings = Vec::new();
+        let (string, _) = rule.pattern(&pattern).string(0, &mut warnings).unwrap();
+        (string, warnings)
+    }
+
+    #[test]
+    fn export_rule() {
+        let header = Pattern::<u8>::from_str("^ 0x4d 0x5a").unwrap();
+        let call = Pattern::<u8>::from_str("0xe8 0x?? [2-4] (0x6a|0x6b) u16le:0x1234").unwrap();
+        let export = YaraRule::new("sample")
+            .meta("author", "a \"b\"")
+            .pattern(&header)
+            .pattern(&call)
+            .export()
+            .unwrap();
+        assert_eq!(
+            export.rule,
+            "rule sample\n{\n    meta:\n        author = \"a \\\"b\\\"\"\n\n    strings:\n        \
+             $p0 = { 4D 5A }\n        $p1 = { E8 ?? [2-4] (6A | 6B) 34 12 }\n\n    \
+             condition:\n        $p0 at 0 or $p1\n}\n"
+        );
+        assert!(export.warnings.is_empty());
+
+        assert!(YaraRule::new("1abc").pattern(&header).export().is_err());
+        assert!(YaraRule::new("abc").export().is_err());
+
+        let rule = YaraRule::new("abc").pattern(&header);
+        assert!(matches!(
+            rule.meta("my key", "x").export(),
+            Err(crate::Error::InvalidIdentifier { .. })
+        ));
+        let export = YaraRule::new("abc")
+            .pattern(&header)
+            .meta("note", "a\\b\nc\td\u{1}")
+            .export()
+            .unwrap();
+        assert!(export
+            .rule
+            .contains("        note = \"a\\\\b\\nc\\td\\x01\"\n"));
+
+        let jump = Pattern::<u8>::from_str("0x41 ( 0x42 [2] )").unwrap();
+        assert_eq!(
+            YaraRule::new("abc")
+                .pattern(&jump)
+                .export()
+                .unwrap_err()
+                .to_string(),
+            "Element ( 0x42 [2] ) at index 1 can not be written in this format"
+        );
+    }
+
+    #[test]
+    fn partial_masks() {
+        // Split into alternatives without losing precision
+        assert_eq!(
+            hex_string("0b0100000? 0x41"),
+            ("{ (40 | 41) 41 }".to_string(), vec![])
+        );
+        assert_eq!(
+            hex_string("0x30-0x3f !0x00 0x41"),
+            ("{ 3? ~00 41 }".to_string(), vec![])
+        );
+
+        // Widened with a warning
+        let (string, warnings) = hex_string("0b0100???? 0b?????1?1");
+        assert_eq!(string, "{ 4? ?? }");
+        assert_eq!(warnings, vec!["$p0: 0b?????1?1 widened to ??".to_string()]);
+
+        let (string, warnings) = hex_string("0x41 0x42{1,3} $ 0x43");
+        assert_eq!(string, "{ 41 42 [0-2] 43 }");
+        assert_eq!(warnings.len(), 2);
+    }
+}
@@ -1,5 +1,5 @@
 use ppatch::prelude::*;
-use ppatch::PatternSearchType;
+use ppatch::{Captures, PatternSearchType};
 use std::io::Write;
 use std::str::FromStr;
 
@@ -57,40 +57,42 @@ fn test_multi() {
     let mut writer = Vec::new();
 
     for item in INPUT
-        .into_iter()
+        .iter()
         .search_pattern(&search) // pattern_search_ref_iterator
         .inspect(|result| match result {
-            PatternSearchType::Match { ref data, index } => {
-                let input_slice = &INPUT[*index..(index + search.len())];
-                assert_eq!(data.len(), input_slice.len());
-                assert_eq!(slice_equality(&data, input_slice), data.len());
+            PatternSearchType::Match {
+                ref data, index, ..
+            } => {
+                let input_slice = &INPUT[*index..(index + data.len())];
+                assert!(data.len() >= search.min_len());
+                assert!(search.max_len().is_none_or(|max| data.len() <= max));
+                assert_eq!(slice_equality(data, input_slice), data.len());
             }
             PatternSearchType::NonMatch(_) => {}
         })
         .replace_pattern(&replace) // pattern_replace_iterator
         .inspect(|result| match result {
             Ok(_) => {}
-            Err(error) => assert!(false, "{}", error),
+            Err(error) => panic!("{}", error),
         })
         .search_pattern(&search) // pattern_search_result_iterator
         .inspect(|result| match result {
             Ok(search_type) => {
-                if let PatternSearchType::Match { data, index } = search_type {
-                    assert!(
-                        false,
+                if let PatternSearchType::Match { data, index, .. } = search_type {
+                    panic!(
                         "There should be no match on second round: {:#X} {:X?}",
                         index, data
                     );
                 }
             }
-            Err(error) => assert!(false, "{}", error),
+            Err(error) => panic!("{}", error),
         })
         .replace_pattern(&replace)
     // pattern_replace_result_iterator
     {
         match item {
             Err(error) => {
-                assert!(false, "{}", error);
+                panic!("{}", error);
             }
             Ok(value) => {
                 let _ = writer.write_all(&[value]);
@@ -101,3 +103,52 @@ fn test_multi() {
     assert_eq!(writer.len(), RESULT.len());
     assert_eq!(slice_equality(&writer, &RESULT), RESULT.len());
 }
+
+#[test]
+fn test_overlapping() {
+    let search = ppatch::Pattern::from_str("0x41 0x41").unwrap();
+    let replace = ppatch::Pattern::from_str("0x61 0x62").unwrap();
+    let input = [0x41, 0x41, 0x41, 0x42, 0x41, 0x41];
+
+    let indices: Vec<usize> = input
+        .iter()
+        .search_pattern_overlapping(&search)
+        .filter_map(|result| match result {
+            PatternSearchType::Match { index, .. } => Some(index),
+            PatternSearchType::NonMatch(_) => None,
+        })
+        .collect();
+    assert_eq!(indices, [0, 1, 4]);
+
+    // Matches overlapping an earlier match are not replaced
+    let result: Vec<u8> = input
+        .iter()
+        .search_pattern_overlapping(&search)
+        .replace_pattern(&replace)
+        .map(|result| result.unwrap())
+        .collect();
+    assert_eq!(result, [0x61, 0x62, 0x41, 0x42, 0x61, 0x62]);
+
+    // Skipping turns matches back into their bytes without duplicating them
+    let result: Vec<PatternSearchType<u8>> = input
+        .iter()
+        .search_pattern_overlapping(&search)
+        .skip_pattern(2)
+        .collect();
+    assert_eq!(
+        result,
+        [
+            PatternSearchType::NonMatch(0x41),
+            PatternSearchType::NonMatch(0x41),
+            PatternSearchType::NonMatch(0x41),
+            PatternSearchType::NonMatch(0x42),
+            PatternSearchType::Match {
+                data: vec![0x41, 0x41],
+                index: 4,
+                id: 0,
+                mismatches: vec![],
+                captures: Captures::default(),
+            },
+        ]
+    );
+}